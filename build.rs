@@ -1,5 +1,13 @@
 fn main() {
+  // The icon resource only exists for Windows builds, every other target
+  // runs the engine against the fake backend and has no tray.
+  if std::env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("windows") {
+    return;
+  }
+
   let mut res = winres::WindowsResource::new();
   res.set_icon("src/data/icon.ico");
+  // Shown in the tray while borders are paused.
+  res.set_icon_with_id("src/data/icon-paused.ico", "2");
   res.compile().unwrap();
 }
@@ -0,0 +1,83 @@
+// Everything that talks to the window manager goes through `WindowBackend`,
+// so the rules, color resolution and the apply loop don't care whether they
+// run against Win32 or against the in-memory fake used off-Windows.
+
+#[cfg(any(test, not(windows)))]
+mod fake;
+#[cfg(windows)]
+mod win32;
+
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+#[cfg(any(test, not(windows)))]
+pub use fake::{FakeBackend, FakeEventSource};
+#[cfg(windows)]
+pub use win32::{Win32Backend, Win32EventSource};
+
+#[cfg(not(windows))]
+pub type PlatformBackend = FakeBackend;
+#[cfg(windows)]
+pub type PlatformBackend = Win32Backend;
+#[cfg(not(windows))]
+pub type PlatformEventSource = FakeEventSource;
+#[cfg(windows)]
+pub type PlatformEventSource = Win32EventSource;
+
+/// Window handle, stored as an integer so it can cross threads and platforms.
+pub type Hwnd = isize;
+
+pub const WS_EX_TOOLWINDOW: u32 = 0x00000080;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowInfo {
+  pub hwnd: Hwnd,
+  pub pid: u32,
+  pub title: String,
+  pub class: String,
+  pub ex_style: u32,
+}
+
+impl WindowInfo {
+  pub fn is_tool_window(&self) -> bool {
+    self.ex_style & WS_EX_TOOLWINDOW != 0
+  }
+}
+
+pub trait WindowBackend {
+  /// All visible top-level windows, including tool windows.
+  fn visible_windows(&self) -> Vec<WindowInfo>;
+  /// Same as a single entry of `visible_windows`, `None` if the window is
+  /// gone or hidden.
+  fn window_info(&self, hwnd: Hwnd) -> Option<WindowInfo>;
+  /// Whether `hwnd` still exists, hidden or not.
+  fn is_window(&self, hwnd: Hwnd) -> bool;
+  fn foreground_window(&self) -> Option<Hwnd>;
+  fn set_window_attribute(&self, hwnd: Hwnd, attribute: u32, value: u32);
+  /// Current value of a DWM attribute, `None` if it can't be read.
+  fn window_attribute(&self, hwnd: Hwnd, attribute: u32) -> Option<u32>;
+  /// Full image path of a process, uncached, see `ProcessCache`.
+  fn process_path(&self, pid: u32) -> Option<String>;
+  /// The system accent color as a COLORREF, if it can be retrieved.
+  fn accent_color(&self) -> Option<u32>;
+}
+
+// Only produced by the Win32 hooks, the tray, IPC and the config watcher so far.
+#[cfg_attr(not(windows), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowEvent {
+  Foreground(Hwnd),
+  Shown(Hwnd),
+  NameChanged(Hwnd),
+  Destroyed(Hwnd),
+  /// Not a window event, asks for every window to be recolored,
+  /// e.g. after the config was reloaded or the accent color changed.
+  Refresh,
+}
+
+pub trait EventSource {
+  /// Blocks until the next event, or returns `None` once `timeout` elapsed.
+  fn next_event(&self, timeout: Option<Duration>) -> Option<WindowEvent>;
+  /// Handle for pushing events into this source from other threads.
+  fn sender(&self) -> Sender<WindowEvent>;
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{EventSource, WindowEvent};
+use super::{Hwnd, WindowBackend, WindowInfo};
+
+#[derive(Default)]
+struct FakeState {
+  windows: Vec<WindowInfo>,
+  hidden: Vec<Hwnd>,
+  foreground: Option<Hwnd>,
+  accent_color: Option<u32>,
+  process_paths: HashMap<u32, String>,
+  attributes: HashMap<(Hwnd, u32), u32>,
+  attribute_calls: usize,
+}
+
+/// In-memory window set, used wherever there is no Win32 to talk to.
+/// Windows are scripted through the helpers below and every attribute
+/// write is recorded instead of being sent to DWM.
+#[derive(Default)]
+pub struct FakeBackend {
+  state: Mutex<FakeState>,
+}
+
+// Scripting helpers for tests, the engine itself only goes through `WindowBackend`.
+#[cfg_attr(not(test), allow(dead_code))]
+impl FakeBackend {
+  pub fn add_window(&self, window: WindowInfo) {
+    let mut state = self.state.lock().unwrap();
+    state.windows.retain(|w| w.hwnd != window.hwnd);
+    state.windows.push(window);
+  }
+
+  pub fn remove_window(&self, hwnd: Hwnd) {
+    let mut state = self.state.lock().unwrap();
+    state.windows.retain(|w| w.hwnd != hwnd);
+    state.attributes.retain(|(h, _), _| *h != hwnd);
+    if state.foreground == Some(hwnd) {
+      state.foreground = None;
+    }
+  }
+
+  /// Hidden windows still exist but aren't listed or looked up, like
+  /// minimized to the tray.
+  pub fn set_hidden(&self, hwnd: Hwnd, hidden: bool) {
+    let mut state = self.state.lock().unwrap();
+    state.hidden.retain(|h| *h != hwnd);
+    if hidden {
+      state.hidden.push(hwnd);
+    }
+  }
+
+  pub fn set_foreground(&self, hwnd: Option<Hwnd>) {
+    self.state.lock().unwrap().foreground = hwnd;
+  }
+
+  pub fn set_accent_color(&self, color: Option<u32>) {
+    self.state.lock().unwrap().accent_color = color;
+  }
+
+  pub fn set_process_path(&self, pid: u32, path: &str) {
+    let mut state = self.state.lock().unwrap();
+    state.process_paths.insert(pid, path.to_string());
+  }
+
+  /// Last value written for `attribute` on `hwnd`.
+  pub fn attribute(&self, hwnd: Hwnd, attribute: u32) -> Option<u32> {
+    let state = self.state.lock().unwrap();
+    state.attributes.get(&(hwnd, attribute)).copied()
+  }
+
+  /// Number of `set_window_attribute` calls so far.
+  pub fn attribute_calls(&self) -> usize {
+    self.state.lock().unwrap().attribute_calls
+  }
+}
+
+impl WindowBackend for FakeBackend {
+  fn visible_windows(&self) -> Vec<WindowInfo> {
+    let state = self.state.lock().unwrap();
+    let visible = state
+      .windows
+      .iter()
+      .filter(|w| !state.hidden.contains(&w.hwnd));
+    visible.cloned().collect()
+  }
+
+  fn window_info(&self, hwnd: Hwnd) -> Option<WindowInfo> {
+    let state = self.state.lock().unwrap();
+    if state.hidden.contains(&hwnd) {
+      return None;
+    }
+    state.windows.iter().find(|w| w.hwnd == hwnd).cloned()
+  }
+
+  fn is_window(&self, hwnd: Hwnd) -> bool {
+    let state = self.state.lock().unwrap();
+    state.windows.iter().any(|w| w.hwnd == hwnd)
+  }
+
+  fn foreground_window(&self) -> Option<Hwnd> {
+    self.state.lock().unwrap().foreground
+  }
+
+  fn set_window_attribute(&self, hwnd: Hwnd, attribute: u32, value: u32) {
+    let mut state = self.state.lock().unwrap();
+    state.attribute_calls += 1;
+    state.attributes.insert((hwnd, attribute), value);
+  }
+
+  fn window_attribute(&self, hwnd: Hwnd, attribute: u32) -> Option<u32> {
+    self.attribute(hwnd, attribute)
+  }
+
+  fn process_path(&self, pid: u32) -> Option<String> {
+    self.state.lock().unwrap().process_paths.get(&pid).cloned()
+  }
+
+  fn accent_color(&self) -> Option<u32> {
+    self.state.lock().unwrap().accent_color
+  }
+}
+
+/// Event stream fed by hand through `sender()`.
+pub struct FakeEventSource {
+  sender: Sender<WindowEvent>,
+  receiver: Receiver<WindowEvent>,
+}
+
+impl FakeEventSource {
+  pub fn new() -> Self {
+    let (sender, receiver) = channel();
+    FakeEventSource { sender, receiver }
+  }
+}
+
+impl EventSource for FakeEventSource {
+  fn next_event(&self, timeout: Option<Duration>) -> Option<WindowEvent> {
+    match timeout {
+      Some(timeout) => self.receiver.recv_timeout(timeout).ok(),
+      None => self.receiver.recv().ok(),
+    }
+  }
+
+  fn sender(&self) -> Sender<WindowEvent> {
+    self.sender.clone()
+  }
+}
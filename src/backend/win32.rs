@@ -0,0 +1,340 @@
+use lazy_static::lazy_static;
+use std::ffi::c_ulong;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::os::windows::prelude::{OsStrExt, OsStringExt};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+use winapi::ctypes::c_int;
+use winapi::ctypes::c_void;
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::ntdef::LONG;
+use winapi::shared::windef::{HWINEVENTHOOK, HWND};
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::dwmapi::{DwmGetColorizationColor, DwmGetWindowAttribute, DwmSetWindowAttribute};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::processthreadsapi::OpenProcess;
+use winapi::um::winbase::QueryFullProcessImageNameW;
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+use winapi::um::winuser::{
+  CreateWindowExW, DefWindowProcW, DispatchMessageW, EnumWindows, GetAncestor, GetClassNameW,
+  GetForegroundWindow, GetMessageW, GetWindowLongW, GetWindowTextLengthW, GetWindowTextW,
+  GetWindowThreadProcessId, IsWindow, IsWindowVisible, RegisterClassW, SetWinEventHook,
+  TranslateMessage, CHILDID_SELF, EVENT_OBJECT_CREATE, EVENT_OBJECT_DESTROY,
+  EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_SHOW, EVENT_SYSTEM_FOREGROUND, GA_ROOT, GWL_EXSTYLE,
+  OBJID_WINDOW, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS, WM_DWMCOLORIZATIONCOLORCHANGED,
+  WM_SETTINGCHANGE, WNDCLASSW,
+};
+
+use super::{EventSource, Hwnd, WindowBackend, WindowEvent, WindowInfo};
+use crate::logger::Logger;
+
+lazy_static! {
+  // WinEvent callbacks don't get a context pointer, so they need a global.
+  static ref HOOK_SENDER: Mutex<Option<Sender<WindowEvent>>> = Mutex::new(None);
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Win32Backend;
+
+unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+  if IsWindowVisible(hwnd) != 0 {
+    let visible_windows: &mut Vec<WindowInfo> = &mut *(lparam as *mut Vec<WindowInfo>);
+    visible_windows.push(window_info(hwnd));
+  }
+
+  1
+}
+
+unsafe fn window_info(hwnd: HWND) -> WindowInfo {
+  let mut title_buffer: [u16; 512] = [0; 512];
+  let text_length = GetWindowTextLengthW(hwnd) + 1;
+  let mut title_length = 0;
+  if text_length > 0 {
+    title_length = GetWindowTextW(
+      hwnd,
+      title_buffer.as_mut_ptr(),
+      text_length.min(title_buffer.len() as c_int),
+    );
+  }
+  let title = OsString::from_wide(&title_buffer[..title_length.max(0) as usize])
+    .to_string_lossy()
+    .into_owned();
+
+  let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+
+  let mut pid: DWORD = 0;
+  GetWindowThreadProcessId(hwnd, &mut pid);
+
+  let mut class_buffer: [u16; 256] = [0; 256];
+  let class_result = GetClassNameW(hwnd, class_buffer.as_mut_ptr(), class_buffer.len() as c_int);
+  let mut class = String::new();
+  if class_result > 0 {
+    class = OsString::from_wide(&class_buffer[..class_result as usize])
+      .to_string_lossy()
+      .into_owned();
+  }
+
+  WindowInfo {
+    hwnd: hwnd as Hwnd,
+    pid,
+    title,
+    class,
+    ex_style,
+  }
+}
+
+impl WindowBackend for Win32Backend {
+  fn visible_windows(&self) -> Vec<WindowInfo> {
+    let mut visible_windows: Vec<WindowInfo> = Vec::new();
+    unsafe {
+      EnumWindows(
+        Some(enum_windows_callback),
+        &mut visible_windows as *mut _ as LPARAM,
+      );
+    }
+    visible_windows
+  }
+
+  fn window_info(&self, hwnd: Hwnd) -> Option<WindowInfo> {
+    unsafe {
+      let hwnd = hwnd as HWND;
+      if IsWindow(hwnd) != 0 && IsWindowVisible(hwnd) != 0 {
+        Some(window_info(hwnd))
+      } else {
+        None
+      }
+    }
+  }
+
+  fn is_window(&self, hwnd: Hwnd) -> bool {
+    unsafe { IsWindow(hwnd as HWND) != 0 }
+  }
+
+  fn foreground_window(&self) -> Option<Hwnd> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+      None
+    } else {
+      Some(hwnd as Hwnd)
+    }
+  }
+
+  fn set_window_attribute(&self, hwnd: Hwnd, attribute: u32, value: u32) {
+    unsafe {
+      DwmSetWindowAttribute(
+        hwnd as HWND,
+        attribute,
+        &value as *const _ as *const c_void,
+        std::mem::size_of::<c_ulong>() as u32,
+      );
+    }
+  }
+
+  fn window_attribute(&self, hwnd: Hwnd, attribute: u32) -> Option<u32> {
+    let mut value: u32 = 0;
+    let result = unsafe {
+      DwmGetWindowAttribute(
+        hwnd as HWND,
+        attribute,
+        &mut value as *mut _ as *mut c_void,
+        std::mem::size_of::<c_ulong>() as u32,
+      )
+    };
+    if SUCCEEDED(result) {
+      Some(value)
+    } else {
+      None
+    }
+  }
+
+  fn process_path(&self, pid: u32) -> Option<String> {
+    unsafe {
+      let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+      if process.is_null() {
+        return None;
+      }
+
+      let mut path_buffer: [u16; 1024] = [0; 1024];
+      let mut path_length = path_buffer.len() as DWORD;
+      let result =
+        QueryFullProcessImageNameW(process, 0, path_buffer.as_mut_ptr(), &mut path_length);
+      CloseHandle(process);
+
+      if result == 0 {
+        return None;
+      }
+      Some(
+        OsString::from_wide(&path_buffer[..path_length as usize])
+          .to_string_lossy()
+          .into_owned(),
+      )
+    }
+  }
+
+  fn accent_color(&self) -> Option<u32> {
+    let mut colorization: u32 = 0;
+    let mut opaqueblend: BOOL = 0;
+    let result = unsafe { DwmGetColorizationColor(&mut colorization, &mut opaqueblend) };
+    if SUCCEEDED(result) {
+      let red = (colorization & 0x00FF0000) >> 16;
+      let green = (colorization & 0x0000FF00) >> 8;
+      let blue = colorization & 0x000000FF;
+      Some((blue << 16) | (green << 8) | red)
+    } else {
+      Logger::log(&format!(
+        "[ERROR] Failed to retrieve accent color: 0x{:08X})",
+        result
+      ));
+      None
+    }
+  }
+}
+
+unsafe extern "system" fn win_event_callback(
+  _hook: HWINEVENTHOOK,
+  event: DWORD,
+  hwnd: HWND,
+  id_object: LONG,
+  id_child: LONG,
+  _event_thread: DWORD,
+  _event_time: DWORD,
+) {
+  if hwnd.is_null() || id_object != OBJID_WINDOW || id_child != CHILDID_SELF {
+    return;
+  }
+
+  let event = match event {
+    EVENT_SYSTEM_FOREGROUND => WindowEvent::Foreground(hwnd as Hwnd),
+    EVENT_OBJECT_CREATE | EVENT_OBJECT_SHOW => WindowEvent::Shown(hwnd as Hwnd),
+    EVENT_OBJECT_NAMECHANGE => WindowEvent::NameChanged(hwnd as Hwnd),
+    EVENT_OBJECT_DESTROY => WindowEvent::Destroyed(hwnd as Hwnd),
+    _ => return,
+  };
+
+  // Only top-level windows get borders. Destroyed windows can't be
+  // queried anymore, those are passed along as-is.
+  if !matches!(event, WindowEvent::Destroyed(_)) && GetAncestor(hwnd, GA_ROOT) != hwnd {
+    return;
+  }
+
+  if let Some(sender) = HOOK_SENDER.lock().unwrap().as_ref() {
+    let _ = sender.send(event);
+  }
+}
+
+unsafe extern "system" fn settings_window_proc(
+  hwnd: HWND,
+  message: UINT,
+  wparam: WPARAM,
+  lparam: LPARAM,
+) -> LRESULT {
+  // The accent color (and `accent` borders with it) changed, or the theme.
+  if message == WM_DWMCOLORIZATIONCOLORCHANGED || message == WM_SETTINGCHANGE {
+    if let Some(sender) = HOOK_SENDER.lock().unwrap().as_ref() {
+      let _ = sender.send(WindowEvent::Refresh);
+    }
+  }
+  DefWindowProcW(hwnd, message, wparam, lparam)
+}
+
+// Accent color and setting changes are only broadcast to top-level windows,
+// not to message-only ones. This one is never shown, so it doesn't get a
+// border either.
+unsafe fn create_settings_window() {
+  let name: Vec<u16> = OsStr::new("cute-borders-settings")
+    .encode_wide()
+    .chain(std::iter::once(0))
+    .collect();
+  let instance = GetModuleHandleW(std::ptr::null());
+  let mut class: WNDCLASSW = std::mem::zeroed();
+  class.lpfnWndProc = Some(settings_window_proc);
+  class.hInstance = instance;
+  class.lpszClassName = name.as_ptr();
+  RegisterClassW(&class);
+
+  let hwnd = CreateWindowExW(
+    0,
+    name.as_ptr(),
+    name.as_ptr(),
+    0,
+    0,
+    0,
+    0,
+    0,
+    std::ptr::null_mut(),
+    std::ptr::null_mut(),
+    instance,
+    std::ptr::null_mut(),
+  );
+  if hwnd.is_null() {
+    Logger::log("[ERROR] Failed to create window for accent color changes");
+  }
+}
+
+/// Foreground, create/show, name change and destroy events from
+/// `SetWinEventHook`, installed on a dedicated thread. Accent color and
+/// setting changes come in as `Refresh`.
+pub struct Win32EventSource {
+  sender: Sender<WindowEvent>,
+  receiver: Receiver<WindowEvent>,
+}
+
+impl Win32EventSource {
+  pub fn new() -> Self {
+    let (sender, receiver) = channel();
+    *HOOK_SENDER.lock().unwrap() = Some(sender.clone());
+
+    std::thread::spawn(|| unsafe {
+      let ranges = [
+        (EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND),
+        (EVENT_OBJECT_CREATE, EVENT_OBJECT_SHOW),
+        (EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_NAMECHANGE),
+      ];
+      for (event_min, event_max) in ranges {
+        let hook = SetWinEventHook(
+          event_min,
+          event_max,
+          std::ptr::null_mut(),
+          Some(win_event_callback),
+          0,
+          0,
+          WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+        if hook.is_null() {
+          Logger::log(&format!(
+            "[ERROR] Failed to install event hook for 0x{:04X}-0x{:04X}",
+            event_min, event_max
+          ));
+        }
+      }
+
+      create_settings_window();
+
+      // Out-of-context hooks and the settings window's messages are
+      // delivered through this thread's message queue.
+      let mut msg = std::mem::zeroed();
+      while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) != 0 {
+        TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+      }
+    });
+
+    Win32EventSource { sender, receiver }
+  }
+}
+
+impl EventSource for Win32EventSource {
+  fn next_event(&self, timeout: Option<Duration>) -> Option<WindowEvent> {
+    match timeout {
+      Some(timeout) => self.receiver.recv_timeout(timeout).ok(),
+      None => self.receiver.recv().ok(),
+    }
+  }
+
+  fn sender(&self) -> Sender<WindowEvent> {
+    self.sender.clone()
+  }
+}
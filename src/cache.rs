@@ -0,0 +1,126 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::backend::{Hwnd, WindowBackend};
+use crate::{DWMWA_BORDER_COLOR, DWMWA_CAPTION_COLOR, DWMWA_COLOR_DEFAULT, DWMWA_TEXT_COLOR};
+
+lazy_static! {
+  static ref CACHE: Mutex<ColorCache> = Mutex::new(ColorCache::default());
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct CacheStats {
+  /// DWM calls that were actually made.
+  pub applied: u64,
+  /// DWM calls skipped because the window already had that value.
+  pub skipped: u64,
+  /// Windows dropped from the cache after being destroyed.
+  pub evicted: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CachedAttribute {
+  current: u32,
+  /// What the window had before we touched it, restored on reset.
+  original: u32,
+}
+
+/// Remembers the last value applied to every window attribute so unchanged
+/// windows don't cost a `DwmSetWindowAttribute` call.
+#[derive(Default)]
+pub struct ColorCache {
+  windows: HashMap<Hwnd, HashMap<u32, CachedAttribute>>,
+  stats: CacheStats,
+}
+
+impl ColorCache {
+  /// `None` means no rule sets the attribute (anymore), which puts it back
+  /// to what it was before we changed it.
+  pub fn set_attribute(
+    backend: &dyn WindowBackend,
+    hwnd: Hwnd,
+    attribute: u32,
+    value: Option<u32>,
+  ) {
+    let mut cache = CACHE.lock().unwrap();
+    let cached = cache
+      .windows
+      .get(&hwnd)
+      .and_then(|attributes| attributes.get(&attribute))
+      .copied();
+
+    let value = match (value, cached) {
+      (Some(value), _) => value,
+      (None, Some(cached)) => cached.original,
+      // Never touched it, nothing to undo.
+      (None, None) => return,
+    };
+
+    if cached.map(|cached| cached.current) == Some(value) {
+      cache.stats.skipped += 1;
+      return;
+    }
+
+    let original = match cached {
+      Some(cached) => cached.original,
+      None => original_value(backend, hwnd, attribute),
+    };
+    backend.set_window_attribute(hwnd, attribute, value);
+    cache.stats.applied += 1;
+    cache.windows.entry(hwnd).or_default().insert(
+      attribute,
+      CachedAttribute {
+        current: value,
+        original,
+      },
+    );
+  }
+
+  /// Puts back every attribute we changed on the window. Borders are always
+  /// reset, even if they were never set.
+  pub fn reset(backend: &dyn WindowBackend, hwnd: Hwnd) {
+    Self::set_attribute(backend, hwnd, DWMWA_BORDER_COLOR, Some(DWMWA_COLOR_DEFAULT));
+
+    let attributes: Vec<u32> = match CACHE.lock().unwrap().windows.get(&hwnd) {
+      Some(attributes) => attributes.keys().copied().collect(),
+      None => Vec::new(),
+    };
+    for attribute in attributes {
+      if attribute != DWMWA_BORDER_COLOR {
+        Self::set_attribute(backend, hwnd, attribute, None);
+      }
+    }
+  }
+
+  pub fn evict(hwnd: Hwnd) {
+    let mut cache = CACHE.lock().unwrap();
+    if cache.windows.remove(&hwnd).is_some() {
+      cache.stats.evicted += 1;
+    }
+  }
+
+  /// Every window with cached attributes, hidden ones included.
+  pub fn hwnds() -> Vec<Hwnd> {
+    CACHE.lock().unwrap().windows.keys().copied().collect()
+  }
+
+  #[cfg(test)]
+  pub fn clear() {
+    CACHE.lock().unwrap().windows.clear();
+  }
+
+  pub fn stats() -> CacheStats {
+    CACHE.lock().unwrap().stats
+  }
+}
+
+/// Colors go back to `DWMWA_COLOR_DEFAULT`, everything else to whatever DWM
+/// reports before the first change (e.g. apps that enable dark mode themselves).
+fn original_value(backend: &dyn WindowBackend, hwnd: Hwnd, attribute: u32) -> u32 {
+  match attribute {
+    DWMWA_BORDER_COLOR | DWMWA_CAPTION_COLOR | DWMWA_TEXT_COLOR => DWMWA_COLOR_DEFAULT,
+    _ => backend.window_attribute(hwnd, attribute).unwrap_or(0),
+  }
+}
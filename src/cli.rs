@@ -0,0 +1,602 @@
+use serde::Serialize;
+use std::io::Write;
+use std::process::ExitCode;
+
+use crate::backend::{Hwnd, WindowBackend, WindowInfo};
+use crate::color::Color;
+use crate::config::{Config, Style};
+use crate::inventory::{colored_windows, inventory};
+use crate::ipc;
+use crate::process::{file_name, ProcessCache};
+use crate::rules::{trace, LiveWindow, Trace, WindowQuery};
+use crate::schema;
+use crate::DWMWA_DEFAULTS;
+
+pub const USAGE: &str = "Usage: cute-borders [command]
+
+Commands:
+  run                      Start cute-borders with the tray icon (default)
+  check-config [file]      Check config.yaml (or file) and print every problem
+  list-windows [--json]    List every window with its class, process and colors
+  explain <hwnd|title>     Show which rules apply to a window and why
+  explain --title <title> [--class <class>] [--process <exe>]
+                           Same for a window that doesn't have to be open
+  reset                    Put every window's colors back to the default, or
+                           pause cute-borders if it is running
+  install                  Start cute-borders on logon (needs administrator)
+  uninstall                Stop starting cute-borders on logon (needs administrator)
+  schema                   Print the JSON Schema for config.yaml
+  version                  Print the version
+  help                     Print this message
+
+Exit codes: 0 on success, 1 if the command failed, 2 for invalid arguments.";
+
+/// Exit code for arguments that don't parse.
+pub const EXIT_USAGE: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+  Run,
+  CheckConfig(Option<String>),
+  /// As JSON instead of a table if `true`.
+  ListWindows(bool),
+  Explain(ExplainTarget),
+  Reset,
+  Install,
+  Uninstall,
+  Schema,
+  Version,
+  Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplainTarget {
+  /// A window handle (decimal or 0x hex) or part of a title.
+  Window(String),
+  Query(WindowQuery),
+}
+
+/// `args` without the executable itself. No arguments is `run`, so the
+/// startup task and double-clicking the exe keep working.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+  let Some((name, rest)) = args.split_first() else {
+    return Ok(Command::Run);
+  };
+  let command = match name.as_str() {
+    "run" => Command::Run,
+    "check-config" => {
+      return match rest {
+        [] => Ok(Command::CheckConfig(None)),
+        [file] => Ok(Command::CheckConfig(Some(file.clone()))),
+        _ => Err(unexpected(&rest[1..])),
+      }
+    }
+    "list-windows" => {
+      return match rest {
+        [] => Ok(Command::ListWindows(false)),
+        [json] if json == "--json" => Ok(Command::ListWindows(true)),
+        rest => Err(unexpected(rest)),
+      }
+    }
+    "explain" => return parse_explain(rest).map(Command::Explain),
+    "reset" => Command::Reset,
+    "install" => Command::Install,
+    "uninstall" => Command::Uninstall,
+    "schema" => Command::Schema,
+    "version" | "--version" | "-V" => Command::Version,
+    "help" | "--help" | "-h" => Command::Help,
+    name => return Err(format!("Unknown command `{}`", name)),
+  };
+  match rest {
+    [] => Ok(command),
+    rest => Err(unexpected(rest)),
+  }
+}
+
+fn parse_explain(args: &[String]) -> Result<ExplainTarget, String> {
+  match args {
+    [] => return Err("`explain` needs a window handle or title".to_string()),
+    [window] if !window.starts_with("--") => return Ok(ExplainTarget::Window(window.clone())),
+    _ => {}
+  }
+  let mut query = WindowQuery::default();
+  let mut args = args.iter();
+  while let Some(flag) = args.next() {
+    let value = args
+      .next()
+      .ok_or_else(|| format!("`{}` needs a value", flag))?
+      .clone();
+    match flag.as_str() {
+      "--title" => query.title = value,
+      "--class" => query.class = value,
+      "--process" => query.process_path = Some(value),
+      flag => return Err(format!("Unexpected argument `{}`", flag)),
+    }
+  }
+  Ok(ExplainTarget::Query(query))
+}
+
+fn unexpected(args: &[String]) -> String {
+  format!("Unexpected argument `{}`", args[0])
+}
+
+/// Runs everything but `run`, which never returns. Output goes to `out`,
+/// failures are returned so `main` can print them and set the exit code.
+pub fn execute(
+  command: &Command,
+  backend: &dyn WindowBackend,
+  out: &mut dyn Write,
+) -> Result<(), String> {
+  match command {
+    Command::Run => unreachable!("`run` is started by main"),
+    Command::CheckConfig(file) => check_config(file.as_deref(), out),
+    Command::ListWindows(json) => list_windows(backend, *json, out),
+    Command::Explain(target) => explain(backend, target, out),
+    Command::Reset => reset(backend, out),
+    Command::Install => set_startup(true),
+    Command::Uninstall => set_startup(false),
+    Command::Schema => print(out, &schema::config_schema()),
+    Command::Version => print(out, &format!("cute-borders {}", env!("CARGO_PKG_VERSION"))),
+    Command::Help => print(out, USAGE),
+  }
+}
+
+pub fn exit_code(result: &Result<(), String>) -> ExitCode {
+  match result {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(_) => ExitCode::FAILURE,
+  }
+}
+
+fn print(out: &mut dyn Write, text: &str) -> Result<(), String> {
+  writeln!(out, "{}", text).map_err(|err| format!("Failed to write output: {}", err))
+}
+
+fn check_config(file: Option<&str>, out: &mut dyn Write) -> Result<(), String> {
+  let config = Config::check(file).map_err(|err| err.to_string())?;
+  for err in &config.errors {
+    print(out, err)?;
+  }
+  match config.errors.len() {
+    0 => print(out, "Config is valid"),
+    1 => Err("Found 1 problem".to_string()),
+    count => Err(format!("Found {} problems", count)),
+  }
+}
+
+fn list_windows(
+  backend: &dyn WindowBackend,
+  json: bool,
+  out: &mut dyn Write,
+) -> Result<(), String> {
+  let config = Config::check(None).map_err(|err| err.to_string())?;
+  let windows = inventory(backend, &config.window_rules);
+  if json {
+    let json = serde_json::to_string_pretty(&windows).map_err(|err| err.to_string())?;
+    return print(out, &json);
+  }
+
+  print(
+    out,
+    &format!(
+      "{:<11} {:<7} {:<20} {:<28} {:<10} {:<28} {:<12} {:<12} TITLE",
+      "HWND", "PID", "PROCESS", "CLASS", "EX_STYLE", "RULES", "ACTIVE", "INACTIVE"
+    ),
+  )?;
+  for window in windows {
+    let (rules, active, inactive) = match &window.resolved {
+      Some(resolved) => {
+        let mut rules = window.rules.clone();
+        if window.override_color.is_some() {
+          rules.insert(0, "override".to_string());
+        }
+        (
+          rules.join(","),
+          border(&resolved.active),
+          border(&resolved.inactive),
+        )
+      }
+      None => (
+        "skipped (tool window)".to_string(),
+        String::new(),
+        String::new(),
+      ),
+    };
+    // The focused window is marked with a *
+    let hwnd = format!(
+      "{:#x}{}",
+      window.hwnd,
+      if window.focused { "*" } else { "" }
+    );
+    print(
+      out,
+      &format!(
+        "{:<11} {:<7} {:<20} {:<28} {:<10} {:<28} {:<12} {:<12} {}",
+        hwnd,
+        window.pid,
+        window.process.as_deref().unwrap_or("unknown"),
+        window.class,
+        format!("{:#010x}", window.ex_style),
+        rules,
+        active,
+        inactive,
+        window.title
+      ),
+    )?;
+  }
+  Ok(())
+}
+
+fn border(style: &Style) -> String {
+  style.border_color.unwrap_or(Color::Default).to_string()
+}
+
+fn parse_hwnd(value: &str) -> Option<Hwnd> {
+  match value
+    .strip_prefix("0x")
+    .or_else(|| value.strip_prefix("0X"))
+  {
+    Some(hex) => Hwnd::from_str_radix(hex, 16).ok(),
+    None => value.parse().ok(),
+  }
+}
+
+/// The window with that handle, otherwise every window whose title
+/// contains `query` (ignoring case).
+fn find_windows(backend: &dyn WindowBackend, query: &str) -> Vec<WindowInfo> {
+  if let Some(window) = parse_hwnd(query).and_then(|hwnd| backend.window_info(hwnd)) {
+    return vec![window];
+  }
+  let query = query.to_lowercase();
+  let mut windows = colored_windows(backend);
+  windows.retain(|window| window.title.to_lowercase().contains(&query));
+  windows
+}
+
+fn explain(
+  backend: &dyn WindowBackend,
+  target: &ExplainTarget,
+  out: &mut dyn Write,
+) -> Result<(), String> {
+  let config = Config::check(None).map_err(|err| err.to_string())?;
+  let query = match target {
+    ExplainTarget::Query(query) => return print_trace(out, &trace(&config.window_rules, query)),
+    ExplainTarget::Window(query) => query,
+  };
+  let windows = find_windows(backend, query);
+  if windows.is_empty() {
+    return Err(format!("No window matches `{}`", query));
+  }
+
+  for window in windows {
+    let process = ProcessCache::path(backend, &window);
+    print(
+      out,
+      &format!(
+        "{:#x} \"{}\" (class {}, process {})",
+        window.hwnd,
+        window.title,
+        window.class,
+        process.as_deref().map(file_name).unwrap_or("unknown")
+      ),
+    )?;
+    if window.is_tool_window() {
+      print(out, "  tool window, cute-borders leaves it alone")?;
+      continue;
+    }
+    let live = LiveWindow::new(backend, &window);
+    print_trace(out, &trace(&config.window_rules, &live))?;
+  }
+  Ok(())
+}
+
+fn print_trace(out: &mut dyn Write, trace: &Trace) -> Result<(), String> {
+  for step in &trace.steps {
+    let line = format!(
+      "  {:<40} {:<12} {}",
+      step.rule,
+      step.outcome.to_string(),
+      step.contributed.join(", ")
+    );
+    print(out, line.trim_end())?;
+  }
+  print(
+    out,
+    &format!("  active:   {}", describe(&trace.resolved.active)),
+  )?;
+  print(
+    out,
+    &format!("  inactive: {}", describe(&trace.resolved.inactive)),
+  )
+}
+
+/// Everything a style sets, the border is always there since it falls back
+/// to the default one.
+fn describe(style: &Style) -> String {
+  let mut fields = vec![format!(
+    "border {}",
+    style
+      .border_color
+      .map(|color| color.to_string())
+      .unwrap_or("default".to_string())
+  )];
+  if let Some(color) = style.caption_color {
+    fields.push(format!("caption {}", color));
+  }
+  if let Some(color) = style.text_color {
+    fields.push(format!("text {}", color));
+  }
+  if let Some(corner) = style.corner_preference {
+    fields.push(format!("corner {}", name(corner)));
+  }
+  if let Some(backdrop) = style.backdrop {
+    fields.push(format!("backdrop {}", name(backdrop)));
+  }
+  if let Some(dark_mode) = style.dark_mode {
+    fields.push(format!("dark_mode {}", dark_mode));
+  }
+  fields.join(", ")
+}
+
+/// How a config enum is written in config.yaml.
+fn name(value: impl Serialize) -> String {
+  match serde_json::to_value(value) {
+    Ok(serde_json::Value::String(name)) => name,
+    _ => String::new(),
+  }
+}
+
+// A running cute-borders knows what every window had before it changed it
+// and would color them again right away, so it is paused instead. This
+// process never colored anything, so without one every attribute is put
+// back to what Windows uses when nothing set it.
+fn reset(backend: &dyn WindowBackend, out: &mut dyn Write) -> Result<(), String> {
+  let request = serde_json::json!({ "command": "pause" });
+  match ipc::send(&request) {
+    Ok(None) => {}
+    Ok(Some(response)) if response["ok"] == true => {
+      return print(
+        out,
+        "cute-borders is running, paused it instead. Resume from the tray or with the pause hotkey",
+      );
+    }
+    Ok(Some(response)) => {
+      return Err(format!(
+        "Failed to pause the running cute-borders: {}",
+        response["error"]
+      ))
+    }
+    Err(err) => return Err(format!("Failed to reach the running cute-borders: {}", err)),
+  }
+
+  for window in colored_windows(backend) {
+    for (attribute, value) in DWMWA_DEFAULTS {
+      backend.set_window_attribute(window.hwnd, attribute, value);
+    }
+  }
+  Ok(())
+}
+
+#[cfg(windows)]
+fn set_startup(enabled: bool) -> Result<(), String> {
+  use check_elevation::is_elevated;
+
+  // set_startup quietly does nothing without elevation, the tray relaunches
+  // itself elevated instead.
+  if !is_elevated().unwrap_or(false) {
+    return Err("This needs to be run as administrator".to_string());
+  }
+  crate::startup::set_startup(enabled)
+    .map_err(|err| format!("Failed to create or update startup task: {}", err))
+}
+
+#[cfg(not(windows))]
+fn set_startup(_enabled: bool) -> Result<(), String> {
+  Err("Starting on logon is only supported on Windows".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use super::*;
+  use crate::backend::{FakeBackend, WindowEvent};
+  use crate::pause::Pause;
+  use crate::testing::{self, window};
+  use crate::util::get_file_path;
+  use crate::DWMWA_BORDER_COLOR;
+
+  // Still uses the version 1 keys, running a command must not upgrade it.
+  const CONFIG: &str = r##"# Mine
+hide_tray_icon: true
+window_rules:
+  - match: "Global"
+    active_border_color: "#ff0000"
+    inactive_border_color: "#0000ff"
+  - match: "Class"
+    contains: "MozillaWindowClass"
+    active_border_color: "#c6a0f6"
+"##;
+
+  fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  fn run(command: &[&str], backend: &FakeBackend) -> (Result<(), String>, String) {
+    let command = parse(&args(command)).unwrap();
+    let mut out = Vec::new();
+    let result = execute(&command, backend, &mut out);
+    (result, String::from_utf8(out).unwrap())
+  }
+
+  #[test]
+  fn commands() {
+    assert_eq!(parse(&[]), Ok(Command::Run));
+    assert_eq!(parse(&args(&["--version"])), Ok(Command::Version));
+    assert_eq!(
+      parse(&args(&["check-config", "other.yaml"])),
+      Ok(Command::CheckConfig(Some("other.yaml".to_string())))
+    );
+    assert_eq!(
+      parse(&args(&["list-windows", "--json"])),
+      Ok(Command::ListWindows(true))
+    );
+    assert_eq!(
+      parse(&args(&["explain", "0x1a2b"])),
+      Ok(Command::Explain(ExplainTarget::Window(
+        "0x1a2b".to_string()
+      )))
+    );
+    assert_eq!(
+      parse(&args(&[
+        "explain",
+        "--title",
+        "Firefox",
+        "--process",
+        "firefox.exe"
+      ])),
+      Ok(Command::Explain(ExplainTarget::Query(WindowQuery {
+        title: "Firefox".to_string(),
+        class: String::new(),
+        process_path: Some("firefox.exe".to_string()),
+      })))
+    );
+  }
+
+  #[test]
+  fn invalid_arguments() {
+    assert_eq!(
+      parse(&args(&["colour"])),
+      Err("Unknown command `colour`".to_string())
+    );
+    assert_eq!(
+      parse(&args(&["version", "--json"])),
+      Err("Unexpected argument `--json`".to_string())
+    );
+    assert_eq!(
+      parse(&args(&["check-config", "a.yaml", "b.yaml"])),
+      Err("Unexpected argument `b.yaml`".to_string())
+    );
+    assert_eq!(
+      parse(&args(&["explain"])),
+      Err("`explain` needs a window handle or title".to_string())
+    );
+    assert_eq!(
+      parse(&args(&["explain", "--title"])),
+      Err("`--title` needs a value".to_string())
+    );
+  }
+
+  #[test]
+  fn printing_commands() {
+    let backend = FakeBackend::default();
+    let (result, out) = run(&["version"], &backend);
+    assert_eq!(result, Ok(()));
+    assert_eq!(out, format!("cute-borders {}\n", env!("CARGO_PKG_VERSION")));
+
+    let (_, out) = run(&["help"], &backend);
+    assert!(out.starts_with("Usage: cute-borders"));
+
+    let (_, out) = run(&["schema"], &backend);
+    assert!(serde_json::from_str::<serde_json::Value>(&out).is_ok());
+  }
+
+  #[test]
+  fn check_config_reports_every_problem() {
+    let _lock = testing::lock();
+    let path = testing::home().join("check-config.yaml");
+    let path = path.to_str().unwrap();
+    let backend = FakeBackend::default();
+
+    fs::write(path, CONFIG).unwrap();
+    let (result, out) = run(&["check-config", path], &backend);
+    assert_eq!(result, Ok(()));
+    assert_eq!(out, "Config is valid\n");
+
+    fs::write(
+      path,
+      CONFIG.replace("#c6a0f6", "#12").replace("#0000ff", "$nope"),
+    )
+    .unwrap();
+    let (result, out) = run(&["check-config", path], &backend);
+    assert_eq!(result, Err("Found 2 problems".to_string()));
+    assert_eq!(out.lines().count(), 2);
+
+    let (result, _) = run(&["check-config", "missing.yaml"], &backend);
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn window_commands_leave_the_config_alone() {
+    let _lock = testing::lock();
+    let path = get_file_path("config.yaml");
+    let backup = get_file_path("config.v1.yaml.bak");
+    fs::write(&path, CONFIG).unwrap();
+    let _ = fs::remove_file(&backup);
+
+    let backend = FakeBackend::default();
+    backend.add_window(window(1, 10, "Mozilla Firefox", "MozillaWindowClass"));
+    backend.add_window(window(2, 20, "Untitled - Notepad", "Notepad"));
+    backend.set_foreground(Some(1));
+
+    let (result, out) = run(&["list-windows"], &backend);
+    assert_eq!(result, Ok(()));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("0x1*"), "{}", lines[1]);
+    assert!(lines[1].contains("#c6a0f6"), "{}", lines[1]);
+    assert!(lines[2].contains("#ff0000"), "{}", lines[2]);
+
+    let (result, out) = run(&["explain", "notepad"], &backend);
+    assert_eq!(result, Ok(()));
+    assert!(out.contains("  active:   border #ff0000\n"), "{}", out);
+    assert!(out.contains("  inactive: border #0000ff\n"), "{}", out);
+
+    let (result, out) = run(&["explain", "--class", "MozillaWindowClass"], &backend);
+    assert_eq!(result, Ok(()));
+    assert!(out.contains("  active:   border #c6a0f6\n"), "{}", out);
+
+    let (result, _) = run(&["explain", "Discord"], &backend);
+    assert_eq!(result, Err("No window matches `Discord`".to_string()));
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+    assert!(!std::path::Path::new(&backup).exists());
+  }
+
+  #[test]
+  fn reset_puts_back_every_attribute_or_pauses() {
+    let _lock = testing::lock();
+    let backend = FakeBackend::default();
+    backend.add_window(window(1, 10, "Mozilla Firefox", "MozillaWindowClass"));
+    for (attribute, _) in DWMWA_DEFAULTS {
+      backend.set_window_attribute(1, attribute, 2);
+    }
+
+    // Nothing is listening yet.
+    let (result, out) = run(&["reset"], &backend);
+    assert_eq!(result, Ok(()));
+    assert_eq!(out, "");
+    for (attribute, value) in DWMWA_DEFAULTS {
+      assert_eq!(
+        backend.attribute(1, attribute),
+        Some(value),
+        "{}",
+        attribute
+      );
+    }
+
+    // The running one puts back what the windows had, so it is left to it.
+    let (events, _receiver) = std::sync::mpsc::channel::<WindowEvent>();
+    ipc::listen(events);
+    backend.set_window_attribute(1, DWMWA_BORDER_COLOR, 2);
+    let (result, out) = run(&["reset"], &backend);
+    assert_eq!(result, Ok(()));
+    assert!(
+      out.starts_with("cute-borders is running, paused it"),
+      "{}",
+      out
+    );
+    assert!(Pause::is_paused());
+    assert_eq!(backend.attribute(1, DWMWA_BORDER_COLOR), Some(2));
+  }
+}
@@ -0,0 +1,449 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+mod named;
+
+use crate::backend::WindowBackend;
+use crate::rainbow::Rainbow;
+use crate::{DWMWA_COLOR_DEFAULT, DWMWA_COLOR_NONE};
+
+/// A color from the config, parsed once when it is loaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+  Rgb(u8, u8, u8),
+  /// The default windows 11 border.
+  Default,
+  /// No border at all.
+  Transparent,
+  /// The system accent color.
+  Accent,
+  /// Cycles through all hues, see `Rainbow`.
+  Rainbow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorErrorKind {
+  Invalid,
+  /// `$name` that isn't in the palette.
+  UnknownVariable,
+  /// Palette variables that end up referencing themselves, e.g. `["$a", "$b", "$a"]`.
+  Cycle(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorParseError {
+  /// The string that failed to parse.
+  pub input: String,
+  pub kind: ColorErrorKind,
+  /// Where in the config it came from, e.g. `window_rules[2].active_border_color`.
+  pub location: Option<String>,
+}
+
+impl ColorParseError {
+  fn new(input: &str) -> Self {
+    ColorParseError {
+      input: input.to_string(),
+      kind: ColorErrorKind::Invalid,
+      location: None,
+    }
+  }
+
+  pub fn unknown_variable(input: &str) -> Self {
+    ColorParseError {
+      kind: ColorErrorKind::UnknownVariable,
+      ..Self::new(input)
+    }
+  }
+
+  pub fn cycle(input: &str, path: Vec<String>) -> Self {
+    ColorParseError {
+      kind: ColorErrorKind::Cycle(path),
+      ..Self::new(input)
+    }
+  }
+
+  pub fn at(mut self, location: &str) -> Self {
+    self.location = Some(location.to_string());
+    self
+  }
+}
+
+impl fmt::Display for ColorParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.kind {
+      ColorErrorKind::Invalid => write!(f, "Invalid color `{}`", self.input)?,
+      ColorErrorKind::UnknownVariable => write!(f, "Unknown color variable `{}`", self.input)?,
+      ColorErrorKind::Cycle(path) => write!(
+        f,
+        "Color variables reference each other: {}",
+        path.join(" -> ")
+      )?,
+    }
+    match &self.location {
+      Some(location) => write!(f, " in {}", location),
+      None => Ok(()),
+    }
+  }
+}
+
+impl std::error::Error for ColorParseError {}
+
+/// The other way around from `from_str`, e.g. for printing resolved colors.
+impl fmt::Display for Color {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+      Color::Default => write!(f, "default"),
+      Color::Transparent => write!(f, "transparent"),
+      Color::Accent => write!(f, "accent"),
+      Color::Rainbow => write!(f, "rainbow"),
+    }
+  }
+}
+
+// Written the same way, so JSON output can be pasted back into the config.
+impl Serialize for Color {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl FromStr for Color {
+  type Err = ColorParseError;
+
+  /// Accepts our keywords, `#rgb`, `#rrggbb`, `rgb()`, `hsl()`, `oklch()`
+  /// and CSS color names. Case doesn't matter, alpha is ignored since DWM
+  /// can't do anything with it.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let value = s.trim().to_ascii_lowercase();
+    let color = match value.as_str() {
+      "default" => Some(Color::Default),
+      "transparent" => Some(Color::Transparent),
+      "accent" => Some(Color::Accent),
+      "rainbow" => Some(Color::Rainbow),
+      value => parse_rgb(value).map(|(r, g, b)| Color::Rgb(r, g, b)),
+    };
+    color.ok_or_else(|| ColorParseError::new(s))
+  }
+}
+
+fn parse_rgb(value: &str) -> Option<(u8, u8, u8)> {
+  if let Some(hex) = value.strip_prefix('#') {
+    return parse_hex(hex);
+  }
+  if let Some(args) = function_args(value, "rgb").or_else(|| function_args(value, "rgba")) {
+    let [r, g, b] = args[..] else { return None };
+    return Some((channel(r)?, channel(g)?, channel(b)?));
+  }
+  if let Some(args) = function_args(value, "hsl").or_else(|| function_args(value, "hsla")) {
+    let [h, s, l] = args[..] else { return None };
+    return Some(hsl_to_rgb(hue(h)?, fraction(s)?, fraction(l)?));
+  }
+  if let Some(args) = function_args(value, "oklch") {
+    let [l, c, h] = args[..] else { return None };
+    let chroma = match c.strip_suffix('%') {
+      // 100% is 0.4 in the CSS spec
+      Some(c) => number(c)? / 100.0 * 0.4,
+      None => number(c)?,
+    };
+    return Some(oklch_to_rgb(fraction(l)?, chroma.max(0.0), hue(h)?));
+  }
+  named::lookup(value)
+}
+
+// The alpha of #rgba and #rrggbbaa is dropped like in rgba().
+fn parse_hex(hex: &str) -> Option<(u8, u8, u8)> {
+  // from_str_radix would take a leading `+` as well.
+  if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+    return None;
+  }
+  match hex.len() {
+    // #rgb is short for #rrggbb
+    3 | 4 => {
+      let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+      Some((digit(0)?, digit(1)?, digit(2)?))
+    }
+    6 | 8 => {
+      let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+      Some((pair(0)?, pair(2)?, pair(4)?))
+    }
+    _ => None,
+  }
+}
+
+/// `rgb(1, 2, 3)`, `rgb(1 2 3)` and `rgb(1 2 3 / 50%)` all give `[1, 2, 3]`.
+/// A fourth comma separated value is taken as alpha and dropped as well.
+fn function_args<'a>(value: &'a str, name: &str) -> Option<Vec<&'a str>> {
+  let args = value
+    .strip_prefix(name)?
+    .trim_start()
+    .strip_prefix('(')?
+    .strip_suffix(')')?;
+  let args = args.split('/').next()?;
+  let mut args: Vec<&str> = args
+    .split(|c: char| c == ',' || c.is_whitespace())
+    .filter(|arg| !arg.is_empty())
+    .collect();
+  if args.len() == 4 && value.contains(',') {
+    args.pop();
+  }
+  Some(args)
+}
+
+fn number(value: &str) -> Option<f32> {
+  value.parse::<f32>().ok().filter(|n| n.is_finite())
+}
+
+/// 0-255 or a percentage, clamped like CSS does.
+fn channel(value: &str) -> Option<u8> {
+  let channel = match value.strip_suffix('%') {
+    Some(percentage) => number(percentage)? / 100.0 * 255.0,
+    None => number(value)?,
+  };
+  Some(channel.clamp(0.0, 255.0).round() as u8)
+}
+
+/// A percentage, or a plain number that is already 0-1.
+fn fraction(value: &str) -> Option<f32> {
+  let fraction = match value.strip_suffix('%') {
+    Some(percentage) => number(percentage)? / 100.0,
+    None => number(value)?,
+  };
+  Some(fraction.clamp(0.0, 1.0))
+}
+
+/// Degrees, wrapped into 0-360.
+fn hue(value: &str) -> Option<f32> {
+  let degrees = number(value.strip_suffix("deg").unwrap_or(value))?;
+  Some(degrees.rem_euclid(360.0))
+}
+
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+  let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+  let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+  let m = l - c / 2.0;
+
+  let (r_prime, g_prime, b_prime) = if (0.0..60.0).contains(&h) {
+    (c, x, 0.0)
+  } else if (60.0..120.0).contains(&h) {
+    (x, c, 0.0)
+  } else if (120.0..180.0).contains(&h) {
+    (0.0, c, x)
+  } else if (180.0..240.0).contains(&h) {
+    (0.0, x, c)
+  } else if (240.0..300.0).contains(&h) {
+    (x, 0.0, c)
+  } else {
+    (c, 0.0, x)
+  };
+
+  let r = ((r_prime + m) * 255.0).round() as u8;
+  let g = ((g_prime + m) * 255.0).round() as u8;
+  let b = ((b_prime + m) * 255.0).round() as u8;
+
+  (r, g, b)
+}
+
+/// OKLCH -> OKLab -> linear sRGB -> sRGB, see https://bottosson.github.io/posts/oklab/
+/// Colors outside of sRGB are clamped.
+fn oklch_to_rgb(l: f32, c: f32, h: f32) -> (u8, u8, u8) {
+  let (a, b) = (c * h.to_radians().cos(), c * h.to_radians().sin());
+
+  let l_ = (l + 0.396_337_8 * a + 0.215_803_76 * b).powi(3);
+  let m_ = (l - 0.105_561_35 * a - 0.063_854_17 * b).powi(3);
+  let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+
+  let red = 4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_;
+  let green = -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_;
+  let blue = -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_;
+
+  let gamma = |x: f32| {
+    let x = if x <= 0.003_130_8 {
+      12.92 * x
+    } else {
+      1.055 * x.powf(1.0 / 2.4) - 0.055
+    };
+    (x.clamp(0.0, 1.0) * 255.0).round() as u8
+  };
+  (gamma(red), gamma(green), gamma(blue))
+}
+
+/// The CSS color names `from_str` accepts.
+pub fn color_names() -> impl Iterator<Item = &'static str> {
+  named::names()
+}
+
+impl Color {
+  /// The COLORREF (0x00bbggrr) DWM expects.
+  pub fn to_colorref(self, backend: &dyn WindowBackend) -> u32 {
+    match self {
+      Color::Rgb(r, g, b) => (b as u32) << 16 | (g as u32) << 8 | r as u32,
+      Color::Default => DWMWA_COLOR_DEFAULT,
+      Color::Transparent => DWMWA_COLOR_NONE,
+      // Falls back to the default border instead of the error color,
+      // the config isn't wrong if the accent color can't be retrieved.
+      Color::Accent => backend.accent_color().unwrap_or(DWMWA_COLOR_DEFAULT),
+      Color::Rainbow => Rainbow::get_color(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::FakeBackend;
+
+  fn rgb(value: &str) -> (u8, u8, u8) {
+    match value.parse::<Color>() {
+      Ok(Color::Rgb(r, g, b)) => (r, g, b),
+      other => panic!("`{}` parsed as {:?}", value, other),
+    }
+  }
+
+  // oklch goes through floats, allow for rounding.
+  fn assert_close(value: &str, expected: (u8, u8, u8)) {
+    let (r, g, b) = rgb(value);
+    let close = |a: u8, b: u8| a.abs_diff(b) <= 1;
+    assert!(
+      close(r, expected.0) && close(g, expected.1) && close(b, expected.2),
+      "`{}` is {:?}, expected {:?}",
+      value,
+      (r, g, b),
+      expected
+    );
+  }
+
+  #[test]
+  fn keywords() {
+    assert_eq!("default".parse(), Ok(Color::Default));
+    assert_eq!("Transparent".parse(), Ok(Color::Transparent));
+    assert_eq!(" accent ".parse(), Ok(Color::Accent));
+    assert_eq!("RAINBOW".parse(), Ok(Color::Rainbow));
+  }
+
+  #[test]
+  fn hex() {
+    assert_eq!(rgb("#ff8800"), (255, 136, 0));
+    assert_eq!(rgb("#FF8800"), (255, 136, 0));
+    assert_eq!(rgb("#abc"), (0xaa, 0xbb, 0xcc));
+    assert_eq!(rgb("#000"), (0, 0, 0));
+    assert_eq!(rgb("#abc8"), (0xaa, 0xbb, 0xcc));
+    assert_eq!(rgb("#ff880080"), (255, 136, 0));
+  }
+
+  #[test]
+  fn rgb_function() {
+    assert_eq!(rgb("rgb(255, 0, 128)"), (255, 0, 128));
+    assert_eq!(rgb("rgb(255 0 128)"), (255, 0, 128));
+    assert_eq!(rgb("rgb(255 0 128 / 50%)"), (255, 0, 128));
+    assert_eq!(rgb("rgba(255, 0, 128, 0.5)"), (255, 0, 128));
+    assert_eq!(rgb("RGB( 255 , 0 , 128 )"), (255, 0, 128));
+    assert_eq!(rgb("rgb(100%, 50%, 0%)"), (255, 128, 0));
+    // Out of range values are clamped like in CSS.
+    assert_eq!(rgb("rgb(300, -20, 128.4)"), (255, 0, 128));
+  }
+
+  #[test]
+  fn hsl_function() {
+    assert_eq!(rgb("hsl(0, 100%, 50%)"), (255, 0, 0));
+    assert_eq!(rgb("hsl(120 100% 50%)"), (0, 255, 0));
+    assert_eq!(rgb("hsl(240deg, 100%, 25%)"), (0, 0, 128));
+    assert_eq!(rgb("hsla(-120, 100%, 50%, 0.3)"), (0, 0, 255));
+    assert_eq!(rgb("hsl(0, 0%, 100%)"), (255, 255, 255));
+    // rebeccapurple
+    assert_eq!(rgb("hsl(270, 50%, 40%)"), (102, 51, 153));
+  }
+
+  // Reference values from the CSS Color 4 spec and oklch.com.
+  #[test]
+  fn oklch_function() {
+    assert_close("oklch(100% 0 0)", (255, 255, 255));
+    assert_close("oklch(0% 0 0)", (0, 0, 0));
+    assert_close("oklch(62.8% 0.2577 29.23)", (255, 0, 0));
+    assert_close("oklch(86.64% 0.2948 142.5)", (0, 255, 0));
+    assert_close("oklch(45.2% 0.313 264.05)", (0, 0, 255));
+    assert_close("oklch(0.628 0.2577 29.23deg)", (255, 0, 0));
+    assert_close("oklch(62.8% 64.4% 29.23)", (255, 0, 0));
+  }
+
+  #[test]
+  fn named_colors() {
+    assert_eq!(rgb("rebeccapurple"), (102, 51, 153));
+    assert_eq!(rgb("RebeccaPurple"), (102, 51, 153));
+    assert_eq!(rgb("white"), (255, 255, 255));
+    // Every entry has to be found, the table is binary searched.
+    for name in color_names() {
+      assert!(name.parse::<Color>().is_ok(), "`{}` wasn't found", name);
+    }
+  }
+
+  #[test]
+  fn invalid_colors() {
+    for value in [
+      "",
+      "#ff",
+      "#ff000",
+      "#gg0000",
+      "#+f0000",
+      "#+f+f+f",
+      "#ff00000",
+      "ff0000",
+      "rgb(1, 2)",
+      "rgb(1, 2, x)",
+      "rgb 1 2 3",
+      "hsl(a, 50%, 50%)",
+      "oklch(50% 0.1)",
+      "notacolor",
+    ] {
+      let err = value.parse::<Color>().unwrap_err();
+      assert_eq!(err.kind, ColorErrorKind::Invalid, "{}", value);
+      assert_eq!(err.input, value);
+    }
+  }
+
+  #[test]
+  fn error_messages() {
+    let err = "#ff000".parse::<Color>().unwrap_err();
+    assert_eq!(err.to_string(), "Invalid color `#ff000`");
+    let err = err.at("window_rules[2].active_border_color");
+    assert_eq!(
+      err.to_string(),
+      "Invalid color `#ff000` in window_rules[2].active_border_color"
+    );
+    let err = ColorParseError::cycle("$a", vec!["$a".into(), "$b".into(), "$a".into()]);
+    assert_eq!(
+      err.to_string(),
+      "Color variables reference each other: $a -> $b -> $a"
+    );
+  }
+
+  #[test]
+  fn display_can_be_parsed_again() {
+    for color in [
+      Color::Rgb(255, 136, 0),
+      Color::Default,
+      Color::Transparent,
+      Color::Accent,
+      Color::Rainbow,
+    ] {
+      assert_eq!(color.to_string().parse(), Ok(color));
+    }
+    assert_eq!(Color::Rgb(255, 136, 0).to_string(), "#ff8800");
+  }
+
+  #[test]
+  fn colorref() {
+    let backend = FakeBackend::default();
+    assert_eq!(
+      Color::Rgb(0x12, 0x34, 0x56).to_colorref(&backend),
+      0x00563412
+    );
+    assert_eq!(Color::Default.to_colorref(&backend), DWMWA_COLOR_DEFAULT);
+    assert_eq!(Color::Transparent.to_colorref(&backend), DWMWA_COLOR_NONE);
+    // Without an accent color the default border is used.
+    assert_eq!(Color::Accent.to_colorref(&backend), DWMWA_COLOR_DEFAULT);
+    backend.set_accent_color(Some(0x00abcdef));
+    assert_eq!(Color::Accent.to_colorref(&backend), 0x00abcdef);
+  }
+}
@@ -1,7 +1,21 @@
-use std::{io::Read, sync::Mutex};
+use std::collections::BTreeMap;
+use std::{fmt, fs, io, io::Read, sync::Mutex};
 
-use crate::{logger::Logger, util::get_file};
+use crate::color::Color;
+use crate::hotkey::{Hotkey, DEFAULT_PAUSE_HOTKEY};
+use crate::include;
+use crate::logger::Logger;
+use crate::matcher::MatchMode;
+use crate::migrate::{migrate, Migrated, CURRENT_VERSION};
+use crate::notify;
+use crate::palette::{theme, Palette};
+use crate::palette_file;
+use crate::rules::{compile_rule, Condition};
+use crate::schema::ThemeName;
+use crate::state::State;
+use crate::util::{get_file, get_file_path, resolve_config_path};
 use lazy_static::lazy_static;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 const DEFAULT_CONFIG: &str = include_str!("data/config.yaml");
@@ -10,61 +24,659 @@ lazy_static! {
   static ref CONFIG: Mutex<Config> = Mutex::new(Config::new());
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-// Maybe support Process later.
-// Getting the process name seems annoying.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Clone)]
 pub enum RuleMatch {
   Global,
   Title,
   Class,
+  /// Executable file name, e.g. `firefox.exe`.
+  Process,
+  /// Full executable path.
+  Path,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// DWMWA_WINDOW_CORNER_PREFERENCE
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CornerPreference {
+  Default,
+  Square,
+  Round,
+  RoundSmall,
+}
+
+impl CornerPreference {
+  pub fn value(self) -> u32 {
+    match self {
+      CornerPreference::Default => 0,
+      CornerPreference::Square => 1,
+      CornerPreference::Round => 2,
+      CornerPreference::RoundSmall => 3,
+    }
+  }
+}
+
+/// DWMWA_SYSTEMBACKDROP_TYPE
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Backdrop {
+  Auto,
+  None,
+  Mica,
+  Acrylic,
+  Tabbed,
+}
+
+impl Backdrop {
+  pub fn value(self) -> u32 {
+    match self {
+      Backdrop::Auto => 0,
+      Backdrop::None => 1,
+      Backdrop::Mica => 2,
+      Backdrop::Acrylic => 3,
+      Backdrop::Tabbed => 4,
+    }
+  }
+}
+
+/// `when:` on a rule. Every key that is set has to match, `all`/`any`/`not`
+/// nest further conditions. `match_mode` applies to title/class/process/path.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RuleCondition {
+  pub all: Option<Vec<RuleCondition>>,
+  pub any: Option<Vec<RuleCondition>>,
+  pub not: Option<Box<RuleCondition>>,
+  pub title: Option<String>,
+  pub class: Option<String>,
+  pub process: Option<String>,
+  pub path: Option<String>,
+  pub match_mode: Option<MatchMode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct WindowRule {
   #[serde(rename = "match")]
-  pub rule_match: RuleMatch,
+  pub rule_match: Option<RuleMatch>,
+  #[serde(alias = "pattern")]
   pub contains: Option<String>,
-  pub active_border_color: String,
-  pub inactive_border_color: String,
+  pub match_mode: Option<MatchMode>,
+  pub when: Option<RuleCondition>,
+  /// Higher goes first, defaults to 0.
+  pub priority: Option<i32>,
+  /// Keep looking at lower precedence rules for the colors this one leaves unset.
+  #[serde(rename = "continue")]
+  pub fallthrough: Option<bool>,
+  #[schemars(with = "Option<Color>")]
+  pub active_border_color: Option<String>,
+  #[schemars(with = "Option<Color>")]
+  pub inactive_border_color: Option<String>,
+  #[schemars(with = "Option<Color>")]
+  pub active_caption_color: Option<String>,
+  #[schemars(with = "Option<Color>")]
+  pub inactive_caption_color: Option<String>,
+  #[schemars(with = "Option<Color>")]
+  pub active_text_color: Option<String>,
+  #[schemars(with = "Option<Color>")]
+  pub inactive_text_color: Option<String>,
+  pub active_corner_preference: Option<CornerPreference>,
+  pub inactive_corner_preference: Option<CornerPreference>,
+  pub active_backdrop: Option<Backdrop>,
+  pub inactive_backdrop: Option<Backdrop>,
+  pub active_dark_mode: Option<bool>,
+  pub inactive_dark_mode: Option<bool>,
+  #[serde(skip)]
+  pub condition: Option<Condition>,
+  /// e.g. `window_rules[2]` or `rules/work.yaml:window_rules[0]`, for messages.
+  #[serde(skip)]
+  pub location: String,
+  /// Set for the active profile's rules, they are looked at before the
+  /// main ones.
+  #[serde(skip)]
+  pub from_profile: bool,
+  #[serde(skip)]
+  active_style: Style,
+  #[serde(skip)]
+  inactive_style: Style,
+}
+
+impl WindowRule {
+  pub fn is_global(&self) -> bool {
+    self.rule_match == Some(RuleMatch::Global)
+  }
+  /// What this rule sets for focused (`active`) or unfocused windows.
+  pub fn style(&self, active: bool) -> Style {
+    if active {
+      self.active_style
+    } else {
+      self.inactive_style
+    }
+  }
+  // Parses the colors of one focus state, anything invalid is reported
+  // and replaced with `error_color` so it stands out.
+  fn compile_style(
+    &self,
+    active: bool,
+    palette: &Palette,
+    error_color: Color,
+    errors: &mut Vec<String>,
+  ) -> Style {
+    let prefix = if active { "active" } else { "inactive" };
+    let mut color = |key: &str, value: &Option<String>| {
+      let value = value.as_deref()?;
+      match palette.parse(value) {
+        Ok(color) => Some(color),
+        Err(err) => {
+          let err = err.at(&format!("{}.{}_{}", self.location, prefix, key));
+          errors.push(err.to_string());
+          Some(error_color)
+        }
+      }
+    };
+
+    if active {
+      Style {
+        border_color: color("border_color", &self.active_border_color),
+        caption_color: color("caption_color", &self.active_caption_color),
+        text_color: color("text_color", &self.active_text_color),
+        corner_preference: self.active_corner_preference,
+        backdrop: self.active_backdrop,
+        dark_mode: self.active_dark_mode,
+      }
+    } else {
+      Style {
+        border_color: color("border_color", &self.inactive_border_color),
+        caption_color: color("caption_color", &self.inactive_caption_color),
+        text_color: color("text_color", &self.inactive_text_color),
+        corner_preference: self.inactive_corner_preference,
+        backdrop: self.inactive_backdrop,
+        dark_mode: self.inactive_dark_mode,
+      }
+    }
+  }
+}
+
+/// Everything a rule can set for one focus state, `None` means unset.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Style {
+  pub border_color: Option<Color>,
+  pub caption_color: Option<Color>,
+  pub text_color: Option<Color>,
+  pub corner_preference: Option<CornerPreference>,
+  pub backdrop: Option<Backdrop>,
+  pub dark_mode: Option<bool>,
+}
+
+impl Style {
+  /// Fills in everything that is still unset from `other`.
+  pub fn inherit(&mut self, other: Style) {
+    self.border_color = self.border_color.or(other.border_color);
+    self.caption_color = self.caption_color.or(other.caption_color);
+    self.text_color = self.text_color.or(other.text_color);
+    self.corner_preference = self.corner_preference.or(other.corner_preference);
+    self.backdrop = self.backdrop.or(other.backdrop);
+    self.dark_mode = self.dark_mode.or(other.dark_mode);
+  }
+  /// Names of the fields that differ from `other`.
+  pub fn changed_fields(&self, other: &Style) -> Vec<&'static str> {
+    let fields = [
+      ("border_color", self.border_color != other.border_color),
+      ("caption_color", self.caption_color != other.caption_color),
+      ("text_color", self.text_color != other.text_color),
+      (
+        "corner_preference",
+        self.corner_preference != other.corner_preference,
+      ),
+      ("backdrop", self.backdrop != other.backdrop),
+      ("dark_mode", self.dark_mode != other.dark_mode),
+    ];
+    fields
+      .into_iter()
+      .filter(|(_, changed)| *changed)
+      .map(|(name, _)| name)
+      .collect()
+  }
+  pub fn colors(&self) -> [Option<Color>; 3] {
+    [self.border_color, self.caption_color, self.text_color]
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TrayConfig {
+  pub hide_icon: Option<bool>,
+  /// Pauses and resumes from anywhere, e.g. `ctrl+shift+f9`. Defaults to
+  /// `ctrl+alt+b`, an empty string turns it off.
+  pub pause_hotkey: Option<String>,
+}
+
+impl TrayConfig {
+  /// `None` if it is turned off.
+  pub fn pause_hotkey(&self) -> Result<Option<Hotkey>, String> {
+    match self.pause_hotkey.as_deref().unwrap_or(DEFAULT_PAUSE_HOTKEY) {
+      "" => Ok(None),
+      hotkey => Hotkey::parse(hotkey).map(Some),
+    }
+  }
 }
 
-// Some are Options because i cant be bothered handling config upgrades
-// if they are not defined we just use the default
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RainbowConfig {
+  /// Degrees of hue per tick, defaults to 1.
+  pub speed: Option<f32>,
+}
+
+/// A set of rules to switch to, e.g. while presenting. Its rules go before
+/// the main `window_rules`, its theme and colors override the main ones.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+  #[schemars(with = "Option<ThemeName>")]
+  pub theme: Option<String>,
+  #[schemars(with = "Option<BTreeMap<String, Color>>")]
+  pub colors: Option<BTreeMap<String, String>>,
+  pub window_rules: Option<Vec<WindowRule>>,
+}
+
+// Older configs are upgraded by `migrate` before they get here,
+// anything that isn't defined just uses the default.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
-  pub hide_tray_icon: Option<bool>,
-  pub rainbow_speed: Option<f32>,
+  /// Format of this file, missing means 1. Older ones are upgraded automatically.
+  pub version: Option<u64>,
+  #[serde(default)]
+  pub tray: TrayConfig,
+  #[serde(default)]
+  pub rainbow: RainbowConfig,
+  /// Used in place of colors that fail to parse, red if not set.
+  #[schemars(with = "Option<Color>")]
+  pub error_color: Option<String>,
+  /// Bundled palette whose colors rules can use, e.g. `catppuccin-mocha`.
+  #[schemars(with = "Option<ThemeName>")]
+  pub theme: Option<String>,
+  /// pywal/wallust `colors.json` or base16 scheme whose colors rules can use.
+  pub palette_file: Option<String>,
+  /// Named colors rules can use as `$name`, they can reference each other.
+  #[schemars(with = "Option<BTreeMap<String, Color>>")]
+  pub colors: Option<BTreeMap<String, String>>,
+  /// Files (or globs like `rules/*.yaml`) whose `window_rules` are added after
+  /// the ones below, relative to the .cuteborders folder.
+  pub include: Option<Vec<String>>,
   pub window_rules: Vec<WindowRule>,
+  /// Rule sets that can be switched between from the tray.
+  pub profiles: Option<BTreeMap<String, Profile>>,
+  /// Profile to use until another one is picked from the tray.
+  pub active_profile: Option<String>,
+  /// The profile actually in use, the tray's choice wins over `active_profile`.
+  #[serde(skip)]
+  pub profile: Option<String>,
+  /// Files and folders pulled in by `include`.
+  #[serde(skip)]
+  included_files: Vec<String>,
+  /// `theme`, `palette_file` and `colors` resolved, for colors that don't
+  /// come from rules, like IPC overrides.
+  #[serde(skip)]
+  pub palette: Palette,
+  /// Problems that don't stop the config from loading, like invalid colors
+  /// or patterns. They are logged and replaced, see `compile_rules`.
+  #[serde(skip)]
+  pub errors: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+  Read(std::io::Error),
+  Migrate(String),
+  Include(String),
+  Parse(ParseError),
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ConfigError::Read(err) => write!(f, "Failed to read config file: {}", err),
+      ConfigError::Migrate(err) => write!(f, "Failed to upgrade config file: {}", err),
+      ConfigError::Include(err) => write!(f, "Failed to include config file: {}", err),
+      ConfigError::Parse(err) => match &err.file {
+        Some(file) => write!(f, "Failed to parse {}: {}", file, err),
+        None => write!(f, "Failed to parse config file: {}", err),
+      },
+    }
+  }
+}
+
+/// A YAML or type error, with where it happened pulled out of `serde_yaml`'s message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+  /// Set for included files, `None` is config.yaml.
+  pub file: Option<String>,
+  pub message: String,
+  /// Path to the offending key, e.g. `window_rules[1].active_border_color`.
+  pub key: Option<String>,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+}
+
+impl From<serde_yaml::Error> for ParseError {
+  // serde_yaml formats its errors as "{path}: {message} at line {l} column {c}".
+  fn from(err: serde_yaml::Error) -> Self {
+    let location = err.location();
+    let mut message = err.to_string();
+    if let Some(location) = &location {
+      let at = format!(" at line {} column {}", location.line(), location.column());
+      if let Some(index) = message.find(&at) {
+        message.replace_range(index..index + at.len(), "");
+      }
+    }
+
+    let mut key = None;
+    if let Some((path, rest)) = message.split_once(": ") {
+      let is_path = path
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_.[]".contains(c));
+      if is_path {
+        key = Some(path.to_string());
+        message = rest.to_string();
+      }
+    }
+    // The path points at the struct, the unknown key itself is only in the message.
+    let unknown_field = message
+      .strip_prefix("unknown field `")
+      .and_then(|rest| rest.split('`').next());
+    if let Some(field) = unknown_field {
+      key = Some(match key {
+        Some(path) => format!("{}.{}", path, field),
+        None => field.to_string(),
+      });
+    }
+
+    ParseError {
+      file: None,
+      message,
+      key,
+      line: location.as_ref().map(|location| location.line()),
+      column: location.as_ref().map(|location| location.column()),
+    }
+  }
+}
+
+impl ParseError {
+  pub fn in_file(mut self, file: &str) -> Self {
+    self.file = Some(file.to_string());
+    self
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let (Some(line), Some(column)) = (self.line, self.column) {
+      write!(f, "line {}, column {}: ", line, column)?;
+    }
+    if let Some(key) = &self.key {
+      write!(f, "`{}`: ", key)?;
+    }
+    write!(f, "{}", self.message)
+  }
 }
 
 impl Config {
+  // A broken config never stops cute-borders, the default one is used
+  // until it is fixed (and picked up by the watcher).
   fn new() -> Self {
+    match Self::load() {
+      Ok(config) => config,
+      Err(err) => {
+        report_error(&err, "using the default config until it is fixed");
+        Self::parse(DEFAULT_CONFIG).expect("Default config is invalid")
+      }
+    }
+  }
+  fn load() -> Result<Self, ConfigError> {
     let mut file = get_file("config.yaml", DEFAULT_CONFIG);
     let mut contents = String::new();
-    match file.read_to_string(&mut contents) {
-      Ok(..) => {}
+    file
+      .read_to_string(&mut contents)
+      .map_err(ConfigError::Read)?;
+    if let Some(migrated) = migrate(&contents).map_err(ConfigError::Migrate)? {
+      save_migrated(&contents, &migrated);
+      contents = migrated.contents;
+    }
+    let config = Self::parse(&contents)?;
+    for err in &config.errors {
+      Logger::log(&format!("[ERROR] {}", err));
+    }
+    Ok(config)
+  }
+  /// Loads `path` (config.yaml if `None`) like cute-borders would, without
+  /// upgrading it on disk or touching the running config. The config's
+  /// `errors` hold everything that would have been logged.
+  pub fn check(path: Option<&str>) -> Result<Self, ConfigError> {
+    let contents = match path {
+      Some(path) => fs::read_to_string(path).map_err(ConfigError::Read)?,
+      None => match fs::read_to_string(get_file_path("config.yaml")) {
+        Ok(contents) => contents,
+        // Same as what the first start writes.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => DEFAULT_CONFIG.to_string(),
+        Err(err) => return Err(ConfigError::Read(err)),
+      },
+    };
+    match migrate(&contents).map_err(ConfigError::Migrate)? {
+      Some(migrated) => Self::parse(&migrated.contents),
+      None => Self::parse(&contents),
+    }
+  }
+  pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+    let mut config: Config =
+      serde_yaml::from_str(contents).map_err(|err| ConfigError::Parse(err.into()))?;
+    for (index, rule) in config.window_rules.iter_mut().enumerate() {
+      rule.location = format!("window_rules[{}]", index);
+    }
+    if let Some(patterns) = &config.include {
+      let included = include::load(patterns)?;
+      config.window_rules.extend(included.rules);
+      config.included_files = included.watched;
+    }
+    config.apply_profile();
+    config.compile_rules();
+    if let Err(err) = config.tray.pause_hotkey() {
+      config
+        .errors
+        .push(format!("Invalid tray.pause_hotkey: {}", err));
+    }
+    Ok(config)
+  }
+  // Conditions and colors are compiled once here instead of on every match.
+  // Broken conditions are reported with their location and never match.
+  fn compile_rules(&mut self) {
+    let mut errors = Vec::new();
+    let error_color = self.error_color(&mut errors);
+    let (palette, palette_errors) = Palette::new(&self.palette_variables(&mut errors), error_color);
+    errors.extend(palette_errors.iter().map(|err| err.to_string()));
+
+    for rule in self.window_rules.iter_mut() {
+      match compile_rule(rule) {
+        Ok(condition) => rule.condition = condition,
+        Err(err) => errors.push(format!("Invalid {}: {}", rule.location, err)),
+      }
+      rule.active_style = rule.compile_style(true, &palette, error_color, &mut errors);
+      rule.inactive_style = rule.compile_style(false, &palette, error_color, &mut errors);
+    }
+    self.errors.extend(errors);
+    self.palette = palette;
+  }
+  // Once a profile was picked from the tray, state.yaml decides which one
+  // is used. Its rules win over the main ones, Global ones included.
+  fn apply_profile(&mut self) {
+    let name = match State::load() {
+      Some(state) => state.active_profile,
+      None => self.active_profile.clone(),
+    };
+    let Some(name) = name else {
+      return;
+    };
+    let Some(profile) = self
+      .profiles
+      .as_ref()
+      .and_then(|profiles| profiles.get(&name))
+    else {
+      self.errors.push(format!("Unknown profile `{}`", name));
+      return;
+    };
+
+    let mut rules = profile.window_rules.clone().unwrap_or_default();
+    for (index, rule) in rules.iter_mut().enumerate() {
+      rule.location = format!("profiles.{}.window_rules[{}]", name, index);
+      rule.from_profile = true;
+    }
+    rules.append(&mut self.window_rules);
+    self.window_rules = rules;
+    self.profile = Some(name);
+  }
+  fn active(&self) -> Option<&Profile> {
+    let name = self.profile.as_ref()?;
+    self.profiles.as_ref()?.get(name)
+  }
+  pub fn profile_names(&self) -> Vec<String> {
+    match &self.profiles {
+      Some(profiles) => profiles.keys().cloned().collect(),
+      None => Vec::new(),
+    }
+  }
+  /// Switches to `name` (`None` for no profile), remembers it in state.yaml
+  /// and reloads. `false` if the profile doesn't exist or the reload failed.
+  pub fn set_profile(name: Option<&str>) -> bool {
+    if let Some(name) = name {
+      if !Self::get()
+        .profile_names()
+        .iter()
+        .any(|profile| profile == name)
+      {
+        Logger::log(&format!("[ERROR] Unknown profile `{}`", name));
+        return false;
+      }
+    }
+    State {
+      active_profile: name.map(str::to_string),
+    }
+    .save();
+    Self::reload()
+  }
+  // Later sources override earlier ones, so `colors` can override and
+  // use colors from the theme and palette file. A profile's theme replaces
+  // the main one, its colors go on top of the main ones.
+  fn palette_variables(&self, errors: &mut Vec<String>) -> BTreeMap<String, String> {
+    let profile = self.active().cloned().unwrap_or_default();
+    let mut variables = BTreeMap::new();
+    if let Some(name) = profile.theme.as_ref().or(self.theme.as_ref()) {
+      match theme(name) {
+        Ok(colors) => variables.extend(colors),
+        Err(err) => errors.push(err.to_string()),
+      }
+    }
+    if let Some(path) = &self.palette_file {
+      match palette_file::load(&resolve_config_path(path)) {
+        Ok(colors) => variables.extend(colors),
+        Err(err) => errors.push(err.to_string()),
+      }
+    }
+    variables.extend(self.colors.clone().unwrap_or_default());
+    variables.extend(profile.colors.unwrap_or_default());
+    variables
+  }
+  fn error_color(&self, errors: &mut Vec<String>) -> Color {
+    const RED: Color = Color::Rgb(0xFF, 0, 0);
+    let Some(error_color) = &self.error_color else {
+      return RED;
+    };
+    match error_color.parse() {
+      Ok(color) => color,
       Err(err) => {
-        Logger::log("[ERROR] Failed to read config file");
-        Logger::log(&format!("[DEBUG] {:?}", err));
-        std::process::exit(1);
+        errors.push(err.at("error_color").to_string());
+        RED
       }
     }
-    let config: Config = match serde_yaml::from_str(contents.as_str()) {
-      Ok(config) => config,
+  }
+  /// Swaps in the config from disk. If it can't be read or parsed the
+  /// current one stays active and `false` is returned.
+  pub fn reload() -> bool {
+    match Self::load() {
+      Ok(config) => {
+        *CONFIG.lock().unwrap() = config;
+        notify::clear();
+        true
+      }
       Err(err) => {
-        Logger::log("[ERROR] Failed to parse config file");
-        Logger::log(&format!("[DEBUG] {:?}", err));
-        std::process::exit(1);
+        report_error(&err, "keeping the previous config");
+        false
       }
-    };
-
-    config
+    }
   }
-  pub fn reload() {
-    let mut config = CONFIG.lock().unwrap();
-    *config = Self::new();
+  /// config.yaml and every other file the config was loaded from.
+  pub fn watched_files(&self) -> Vec<String> {
+    let mut files = vec![get_file_path("config.yaml")];
+    files.extend(
+      self
+        .palette_file
+        .iter()
+        .map(|path| resolve_config_path(path)),
+    );
+    files.extend(self.included_files.iter().cloned());
+    files
+  }
+  /// Whether any rule uses a color that changes over time.
+  pub fn is_animated(&self) -> bool {
+    self.window_rules.iter().any(|rule| {
+      [rule.style(true), rule.style(false)]
+        .iter()
+        .flat_map(|style| style.colors())
+        .any(|color| color == Some(Color::Rainbow))
+    })
   }
   pub fn get() -> Self {
     CONFIG.lock().unwrap().clone()
   }
+  #[cfg(test)]
+  pub fn set(config: Config) {
+    *CONFIG.lock().unwrap() = config;
+  }
+}
+
+// Keeps the old file around as config.v1.yaml.bak and so on. If that fails
+// the upgraded config is only used in memory, it is upgraded again next time.
+fn save_migrated(original: &str, migrated: &Migrated) {
+  if !migrated.preserves_text {
+    // Writing it would throw away every comment in the file.
+    Logger::log(&format!(
+      "[INFO] Upgraded config from version {} to {} in memory only, config.yaml couldn't be upgraded without losing its comments",
+      migrated.from, CURRENT_VERSION
+    ));
+    return;
+  }
+  let backup = get_file_path(&format!("config.v{}.yaml.bak", migrated.from));
+  if let Err(err) = fs::write(&backup, original) {
+    Logger::log(&format!(
+      "[ERROR] Failed to back up config file to {}",
+      backup
+    ));
+    Logger::log(&format!("[DEBUG] {:?}", err));
+    return;
+  }
+  if let Err(err) = fs::write(get_file_path("config.yaml"), &migrated.contents) {
+    Logger::log("[ERROR] Failed to write upgraded config file");
+    Logger::log(&format!("[DEBUG] {:?}", err));
+    return;
+  }
+  Logger::log(&format!(
+    "[INFO] Upgraded config from version {} to {}, the old one was saved to {}",
+    migrated.from, CURRENT_VERSION, backup
+  ));
+}
+
+fn report_error(err: &ConfigError, consequence: &str) {
+  Logger::log(&format!("[ERROR] {}, {}", err, consequence));
+  Logger::log(&format!("[DEBUG] {:?}", err));
+  notify::show_error(&format!("{}\n\ncute-borders is {}.", err, consequence));
 }
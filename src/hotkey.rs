@@ -0,0 +1,148 @@
+// Global hotkeys like `ctrl+alt+b`, registered with RegisterHotKey by the
+// tray. Parsing doesn't need Windows, so the config can be checked anywhere.
+
+/// Used when `tray.pause_hotkey` isn't set.
+pub const DEFAULT_PAUSE_HOTKEY: &str = "ctrl+alt+b";
+
+// RegisterHotKey modifiers, same values as winapi's MOD_*.
+pub const MOD_ALT: u32 = 0x0001;
+pub const MOD_CONTROL: u32 = 0x0002;
+pub const MOD_SHIFT: u32 = 0x0004;
+pub const MOD_WIN: u32 = 0x0008;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hotkey {
+  pub modifiers: u32,
+  /// Virtual-key code.
+  pub key: u32,
+}
+
+impl Hotkey {
+  /// Modifiers and one key joined with `+`, ignoring case and spaces, e.g.
+  /// `ctrl+alt+b`, `win+shift+f9` or `ctrl+pause`. At least one modifier is
+  /// needed so typing never triggers it.
+  pub fn parse(value: &str) -> Result<Self, String> {
+    let mut modifiers = 0;
+    let mut key = None;
+    for part in value.split('+') {
+      let part = part.trim().to_lowercase();
+      let modifier = match part.as_str() {
+        "ctrl" | "control" => MOD_CONTROL,
+        "alt" => MOD_ALT,
+        "shift" => MOD_SHIFT,
+        "win" | "super" => MOD_WIN,
+        _ => 0,
+      };
+      if modifier != 0 {
+        modifiers |= modifier;
+        continue;
+      }
+      if key.is_some() {
+        return Err(format!("`{}` has more than one key", value));
+      }
+      key = Some(virtual_key(&part).ok_or_else(|| format!("Unknown key `{}`", part))?);
+    }
+
+    let Some(key) = key else {
+      return Err(format!("`{}` needs a key besides the modifiers", value));
+    };
+    if modifiers == 0 {
+      return Err(format!(
+        "`{}` needs at least one of ctrl, alt, shift or win",
+        value
+      ));
+    }
+    Ok(Hotkey { modifiers, key })
+  }
+}
+
+fn virtual_key(name: &str) -> Option<u32> {
+  let mut chars = name.chars();
+  if let (Some(char), None) = (chars.next(), chars.next()) {
+    if char.is_ascii_alphanumeric() {
+      // VK_A..VK_Z and VK_0..VK_9 are their uppercase ASCII codes.
+      return Some(char.to_ascii_uppercase() as u32);
+    }
+  }
+  if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+    // VK_F1..VK_F24
+    return (1..=24).contains(&number).then_some(0x70 + number - 1);
+  }
+  match name {
+    "space" => Some(0x20),
+    "pause" => Some(0x13),
+    "insert" => Some(0x2d),
+    "delete" => Some(0x2e),
+    "home" => Some(0x24),
+    "end" => Some(0x23),
+    "pageup" => Some(0x21),
+    "pagedown" => Some(0x22),
+    "scrolllock" => Some(0x91),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing;
+
+  #[test]
+  fn modifiers_and_key() {
+    assert_eq!(
+      Hotkey::parse(DEFAULT_PAUSE_HOTKEY),
+      Ok(Hotkey {
+        modifiers: MOD_CONTROL | MOD_ALT,
+        key: 'B' as u32,
+      })
+    );
+    assert_eq!(
+      Hotkey::parse("Win + Shift + F9"),
+      Ok(Hotkey {
+        modifiers: MOD_WIN | MOD_SHIFT,
+        key: 0x78,
+      })
+    );
+    assert_eq!(Hotkey::parse("ctrl+pause").unwrap().key, 0x13);
+    assert_eq!(Hotkey::parse("alt+0").unwrap().key, '0' as u32);
+  }
+
+  #[test]
+  fn invalid_hotkeys() {
+    assert_eq!(
+      Hotkey::parse("b"),
+      Err("`b` needs at least one of ctrl, alt, shift or win".to_string())
+    );
+    assert_eq!(
+      Hotkey::parse("ctrl+alt"),
+      Err("`ctrl+alt` needs a key besides the modifiers".to_string())
+    );
+    assert_eq!(
+      Hotkey::parse("ctrl+a+b"),
+      Err("`ctrl+a+b` has more than one key".to_string())
+    );
+    assert_eq!(
+      Hotkey::parse("ctrl+f25"),
+      Err("Unknown key `f25`".to_string())
+    );
+    assert_eq!(Hotkey::parse("ctrl+"), Err("Unknown key ``".to_string()));
+  }
+
+  #[test]
+  fn pause_hotkey_from_the_config() {
+    let config = testing::config("window_rules: []\n");
+    assert_eq!(
+      config.tray.pause_hotkey(),
+      Ok(Hotkey::parse(DEFAULT_PAUSE_HOTKEY).ok())
+    );
+
+    let config = testing::config("tray:\n  pause_hotkey: \"\"\nwindow_rules: []\n");
+    assert_eq!(config.tray.pause_hotkey(), Ok(None));
+
+    let config = testing::config("tray:\n  pause_hotkey: \"b\"\nwindow_rules: []\n");
+    assert_eq!(
+      config.errors,
+      vec!["Invalid tray.pause_hotkey: `b` needs at least one of ctrl, alt, shift or win"]
+    );
+  }
+}
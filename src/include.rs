@@ -0,0 +1,274 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::config::{ConfigError, ParseError, WindowRule};
+use crate::util::{get_file_path, resolve_config_path};
+
+/// A file pulled in with `include:`, it can include further files.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Fragment {
+  include: Option<Vec<String>>,
+  window_rules: Option<Vec<WindowRule>>,
+}
+
+#[derive(Default)]
+pub struct Included {
+  pub rules: Vec<WindowRule>,
+  /// Every included file plus the directories globs were matched in,
+  /// so new fragments get picked up as well.
+  pub watched: Vec<String>,
+}
+
+/// Loads the rules of every file `patterns` matches. Patterns are relative
+/// to .cuteborders and matches are sorted by path. A file's own rules come
+/// before the rules of the files it includes.
+pub fn load(patterns: &[String]) -> Result<Included, ConfigError> {
+  let mut included = Included::default();
+  let config = canonical(Path::new(&get_file_path("config.yaml")));
+  include(patterns, &mut vec![config], &mut included)?;
+  Ok(included)
+}
+
+fn include(
+  patterns: &[String],
+  stack: &mut Vec<PathBuf>,
+  included: &mut Included,
+) -> Result<(), ConfigError> {
+  for pattern in patterns {
+    for path in expand(pattern, included)? {
+      let path = canonical(&path);
+      if stack.contains(&path) {
+        let mut cycle: Vec<String> = stack.iter().map(|path| display_name(path)).collect();
+        cycle.push(display_name(&path));
+        return Err(ConfigError::Include(format!(
+          "Include cycle: {}",
+          cycle.join(" -> ")
+        )));
+      }
+
+      let name = display_name(&path);
+      let contents = fs::read_to_string(&path)
+        .map_err(|err| ConfigError::Include(format!("Failed to read {}: {}", name, err)))?;
+      let fragment: Fragment = serde_yaml::from_str(&contents)
+        .map_err(|err| ConfigError::Parse(ParseError::from(err).in_file(&name)))?;
+      included.watched.push(path.to_string_lossy().into_owned());
+
+      for (index, mut rule) in fragment
+        .window_rules
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+      {
+        rule.location = format!("{}:window_rules[{}]", name, index);
+        included.rules.push(rule);
+      }
+      if let Some(patterns) = fragment.include {
+        stack.push(path);
+        include(&patterns, stack, included)?;
+        stack.pop();
+      }
+    }
+  }
+  Ok(())
+}
+
+// A plain path has to exist, a glob matching nothing is fine
+// (e.g. an empty rules directory).
+fn expand(pattern: &str, included: &mut Included) -> Result<Vec<PathBuf>, ConfigError> {
+  let resolved = resolve_config_path(pattern);
+  let is_glob = pattern.contains(['*', '?', '[']);
+  if !is_glob {
+    if !Path::new(&resolved).is_file() {
+      return Err(ConfigError::Include(format!(
+        "Included file {} doesn't exist",
+        pattern
+      )));
+    }
+    return Ok(vec![PathBuf::from(resolved)]);
+  }
+
+  let paths = glob::glob(&resolved)
+    .map_err(|err| ConfigError::Include(format!("Invalid include `{}`: {}", pattern, err)))?;
+  let mut paths: Vec<PathBuf> = paths
+    .filter_map(Result::ok)
+    .filter(|path| path.is_file())
+    .collect();
+  paths.sort();
+
+  // The deepest directory without wildcards, it changes when files are added.
+  let dir = Path::new(&resolved)
+    .ancestors()
+    .find(|dir| !dir.to_string_lossy().contains(['*', '?', '[']));
+  if let Some(dir) = dir {
+    included.watched.push(dir.to_string_lossy().into_owned());
+  }
+  Ok(paths)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+  fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Relative to .cuteborders if it is in there, for shorter messages.
+fn display_name(path: &Path) -> String {
+  let dir = canonical(Path::new(&get_file_path("")));
+  path
+    .strip_prefix(&dir)
+    .unwrap_or(path)
+    .to_string_lossy()
+    .replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use super::*;
+  use crate::config::Config;
+  use crate::testing;
+
+  /// Writes `files` relative to .cuteborders.
+  fn write(files: &[(&str, &str)]) {
+    for (name, contents) in files {
+      let path = get_file_path(name);
+      fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+      fs::write(path, contents).unwrap();
+    }
+  }
+
+  fn global(color: &str) -> String {
+    format!(
+      "window_rules:\n  - match: \"Global\"\n    active_border_color: \"{}\"\n",
+      color
+    )
+  }
+
+  fn locations(config: &Config) -> Vec<&str> {
+    config
+      .window_rules
+      .iter()
+      .map(|rule| rule.location.as_str())
+      .collect()
+  }
+
+  fn error(contents: &str) -> String {
+    match Config::parse(contents) {
+      Ok(_) => panic!("Expected an error"),
+      Err(err) => err.to_string(),
+    }
+  }
+
+  #[test]
+  fn globs_are_sorted_and_go_after_the_own_rules() {
+    let _lock = testing::lock();
+    // Written out of order on purpose.
+    write(&[
+      ("order/b.yaml", &global("#00ff00")),
+      ("order/a.yaml", &global("#0000ff")),
+      ("order/c.txt", &global("#ffffff")),
+    ]);
+    let config = testing::config(&format!(
+      "include: [\"order/*.yaml\"]\n{}",
+      global("#ff0000")
+    ));
+    assert_eq!(
+      locations(&config),
+      vec![
+        "window_rules[0]",
+        "order/a.yaml:window_rules[0]",
+        "order/b.yaml:window_rules[0]"
+      ]
+    );
+    assert!(config.errors.is_empty());
+  }
+
+  #[test]
+  fn included_files_can_include_more() {
+    let _lock = testing::lock();
+    write(&[
+      (
+        "nested/outer.yaml",
+        &format!("include: [\"nested/inner.yaml\"]\n{}", global("#00ff00")),
+      ),
+      ("nested/inner.yaml", &global("#0000ff")),
+    ]);
+    let config = testing::config("include: [\"nested/outer.yaml\"]\nwindow_rules: []\n");
+    assert_eq!(
+      locations(&config),
+      vec![
+        "nested/outer.yaml:window_rules[0]",
+        "nested/inner.yaml:window_rules[0]"
+      ]
+    );
+    let watched = config.watched_files();
+    assert!(watched.iter().any(|file| file.ends_with("inner.yaml")));
+  }
+
+  #[test]
+  fn globs_matching_nothing_are_fine() {
+    let _lock = testing::lock();
+    let config = testing::config(&format!(
+      "include: [\"nothing-here/*.yaml\"]\n{}",
+      global("#ff0000")
+    ));
+    assert_eq!(locations(&config), vec!["window_rules[0]"]);
+    // Picked up once files are added.
+    let watched = config.watched_files();
+    assert!(watched.iter().any(|file| file.ends_with("nothing-here")));
+  }
+
+  #[test]
+  fn missing_files_are_errors() {
+    let _lock = testing::lock();
+    assert_eq!(
+      error("include: [\"missing/rules.yaml\"]\nwindow_rules: []\n"),
+      "Failed to include config file: Included file missing/rules.yaml doesn't exist"
+    );
+  }
+
+  #[test]
+  fn cycles_are_errors() {
+    let _lock = testing::lock();
+    write(&[
+      (
+        "cycle/a.yaml",
+        "include: [\"cycle/b.yaml\"]\nwindow_rules: []\n",
+      ),
+      (
+        "cycle/b.yaml",
+        "include: [\"cycle/a.yaml\"]\nwindow_rules: []\n",
+      ),
+    ]);
+    let err = error("include: [\"cycle/a.yaml\"]\nwindow_rules: []\n");
+    assert!(
+      err.starts_with("Failed to include config file: Include cycle: "),
+      "{}",
+      err
+    );
+    assert!(
+      err.ends_with("config.yaml -> cycle/a.yaml -> cycle/b.yaml -> cycle/a.yaml"),
+      "{}",
+      err
+    );
+  }
+
+  #[test]
+  fn parse_errors_name_the_file() {
+    let _lock = testing::lock();
+    write(&[(
+      "broken/rules.yaml",
+      "window_rules:\n  - match: \"Global\"\n    border: \"red\"\n",
+    )]);
+    match Config::parse("include: [\"broken/rules.yaml\"]\nwindow_rules: []\n") {
+      Err(ConfigError::Parse(err)) => {
+        assert_eq!(err.file.as_deref(), Some("broken/rules.yaml"));
+        assert_eq!(err.key.as_deref(), Some("window_rules[0].border"));
+        assert_eq!(err.line, Some(3));
+      }
+      other => panic!("Expected a parse error, got {:?}", other.map(|_| ())),
+    }
+  }
+}
@@ -0,0 +1,91 @@
+use serde::Serialize;
+
+use crate::backend::{Hwnd, WindowBackend, WindowInfo};
+use crate::color::Color;
+use crate::config::WindowRule;
+use crate::overrides::Overrides;
+use crate::process::{file_name, ProcessCache};
+use crate::rules::{trace, LiveWindow, Outcome, Resolved};
+
+/// Everything `list-windows` knows about a window, e.g. to find the class
+/// name to write a rule for.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowEntry {
+  pub hwnd: Hwnd,
+  pub pid: u32,
+  pub title: String,
+  pub class: String,
+  /// Executable name, `None` if it couldn't be looked up.
+  pub process: Option<String>,
+  pub ex_style: u32,
+  /// Tool windows (WS_EX_TOOLWINDOW) are never colored.
+  pub skipped: bool,
+  pub focused: bool,
+  /// Rules that matched and set something, in the order they were applied.
+  pub rules: Vec<String>,
+  /// Border color set over IPC, it wins over the rules.
+  #[serde(rename = "override")]
+  pub override_color: Option<Color>,
+  /// What the window gets right now, `None` for skipped windows.
+  pub resolved: Option<Resolved>,
+}
+
+/// Windows that get colored, tool windows are left out like in `apply_colors`.
+pub fn colored_windows(backend: &dyn WindowBackend) -> Vec<WindowInfo> {
+  let mut windows = backend.visible_windows();
+  windows.retain(|window| !window.is_tool_window());
+  windows
+}
+
+/// Every visible top-level window, including the ones that are skipped.
+pub fn inventory(backend: &dyn WindowBackend, rules: &[WindowRule]) -> Vec<WindowEntry> {
+  let foreground = backend.foreground_window();
+  backend
+    .visible_windows()
+    .into_iter()
+    .map(|window| entry(backend, rules, window, foreground))
+    .collect()
+}
+
+fn entry(
+  backend: &dyn WindowBackend,
+  rules: &[WindowRule],
+  window: WindowInfo,
+  foreground: Option<Hwnd>,
+) -> WindowEntry {
+  let process = ProcessCache::path(backend, &window).map(|path| file_name(&path).to_string());
+  let skipped = window.is_tool_window();
+  let override_color = Overrides::get(window.hwnd);
+
+  let (matched, resolved) = if skipped {
+    (Vec::new(), None)
+  } else {
+    let trace = trace(rules, &LiveWindow::new(backend, &window));
+    let matched = trace
+      .steps
+      .into_iter()
+      .filter(|step| step.outcome == Outcome::Matched && !step.contributed.is_empty())
+      .map(|step| step.rule)
+      .collect();
+    let mut resolved = trace.resolved;
+    if override_color.is_some() {
+      resolved.active.border_color = override_color;
+      resolved.inactive.border_color = override_color;
+    }
+    (matched, Some(resolved))
+  };
+
+  WindowEntry {
+    focused: foreground == Some(window.hwnd),
+    hwnd: window.hwnd,
+    pid: window.pid,
+    title: window.title,
+    class: window.class,
+    process,
+    ex_style: window.ex_style,
+    skipped,
+    rules: matched,
+    override_color,
+    resolved,
+  }
+}
@@ -0,0 +1,691 @@
+// Lets other programs (AutoHotkey scripts, tiling WM hooks, ...) control the
+// running cute-borders. Every line sent is one JSON request and gets one JSON
+// response line back. The protocol only sees readers and writers, the
+// transport is a per-user named pipe on Windows and a Unix socket elsewhere.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use crate::backend::{Hwnd, PlatformBackend, WindowBackend, WindowEvent};
+use crate::cache::ColorCache;
+use crate::config::Config;
+use crate::inventory::inventory;
+use crate::logger::Logger;
+use crate::overrides::Overrides;
+use crate::pause::Pause;
+use crate::rules::{trace, LiveWindow, WindowQuery};
+
+/// e.g. `{"command": "set-profile", "profile": "work"}`
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "command", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Request {
+  Reload,
+  /// No `minutes` pauses until `resume`.
+  Pause {
+    minutes: Option<u64>,
+  },
+  Resume,
+  /// No `profile` switches back to no profile.
+  SetProfile {
+    profile: Option<String>,
+  },
+  /// No `color` removes the override.
+  Override {
+    hwnd: Hwnd,
+    color: Option<String>,
+  },
+  Status,
+  ListWindows,
+  /// Either an open window, or plain values like `explain --title`.
+  Explain {
+    hwnd: Option<Hwnd>,
+    title: Option<String>,
+    class: Option<String>,
+    process: Option<String>,
+  },
+}
+
+pub struct Handler<'a> {
+  backend: &'a dyn WindowBackend,
+  events: Sender<WindowEvent>,
+}
+
+impl<'a> Handler<'a> {
+  pub fn new(backend: &'a dyn WindowBackend, events: Sender<WindowEvent>) -> Self {
+    Handler { backend, events }
+  }
+
+  /// Answers requests until the other side closes the connection.
+  pub fn serve(&self, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+    for line in reader.lines() {
+      let line = line?;
+      if line.trim().is_empty() {
+        continue;
+      }
+      writeln!(writer, "{}", self.handle_line(&line))?;
+      writer.flush()?;
+    }
+    Ok(())
+  }
+
+  /// `{"ok": true, ...}` with whatever the command returns, or
+  /// `{"ok": false, "error": "..."}`.
+  pub fn handle_line(&self, line: &str) -> String {
+    let result = serde_json::from_str(line)
+      .map_err(|err| format!("Invalid request: {}", err))
+      .and_then(|request| self.handle(request));
+    let response = match result {
+      Ok(Value::Object(mut response)) => {
+        response.insert("ok".to_string(), Value::Bool(true));
+        Value::Object(response)
+      }
+      Ok(value) => json!({ "ok": true, "result": value }),
+      Err(err) => json!({ "ok": false, "error": err }),
+    };
+    response.to_string()
+  }
+
+  pub fn handle(&self, request: Request) -> Result<Value, String> {
+    match request {
+      Request::Reload => {
+        if !Config::reload() {
+          return Err("Failed to reload the config, see log.txt".to_string());
+        }
+        self.refresh();
+        Ok(json!({}))
+      }
+      Request::Pause { minutes } => {
+        Pause::pause(minutes.map(|minutes| Duration::from_secs(minutes.saturating_mul(60))));
+        self.refresh();
+        Ok(pause_status())
+      }
+      Request::Resume => {
+        Pause::resume();
+        self.refresh();
+        Ok(pause_status())
+      }
+      Request::SetProfile { profile } => {
+        if let Some(name) = &profile {
+          if !Config::get().profile_names().contains(name) {
+            return Err(format!("Unknown profile `{}`", name));
+          }
+        }
+        if !Config::set_profile(profile.as_deref()) {
+          return Err("Failed to reload the config, see log.txt".to_string());
+        }
+        self.refresh();
+        Ok(json!({ "profile": Config::get().profile }))
+      }
+      Request::Override { hwnd, color } => {
+        if self.backend.window_info(hwnd).is_none() {
+          return Err(format!("No window {:#x}", hwnd));
+        }
+        // `$name` works like it does in rules.
+        let color = match color {
+          Some(color) => Some(
+            Config::get()
+              .palette
+              .parse(&color)
+              .map_err(|err| err.to_string())?,
+          ),
+          None => None,
+        };
+        Overrides::set(hwnd, color);
+        self.refresh();
+        Ok(json!({}))
+      }
+      Request::Status => {
+        let config = Config::get();
+        let mut status = pause_status();
+        status["version"] = json!(env!("CARGO_PKG_VERSION"));
+        status["profile"] = json!(config.profile);
+        status["profiles"] = json!(config.profile_names());
+        status["overrides"] = json!(Overrides::count());
+        status["config_errors"] = json!(config.errors);
+        status["cache"] = json!(ColorCache::stats());
+        Ok(status)
+      }
+      Request::ListWindows => {
+        let windows = inventory(self.backend, &Config::get().window_rules);
+        Ok(json!({ "windows": windows }))
+      }
+      Request::Explain {
+        hwnd,
+        title,
+        class,
+        process,
+      } => {
+        let rules = Config::get().window_rules;
+        let trace = match hwnd {
+          Some(hwnd) => {
+            let window = self
+              .backend
+              .window_info(hwnd)
+              .ok_or_else(|| format!("No window {:#x}", hwnd))?;
+            if window.is_tool_window() {
+              return Err(format!(
+                "Window {:#x} is a tool window, cute-borders leaves it alone",
+                hwnd
+              ));
+            }
+            trace(&rules, &LiveWindow::new(self.backend, &window))
+          }
+          None => {
+            let query = WindowQuery {
+              title: title.unwrap_or_default(),
+              class: class.unwrap_or_default(),
+              process_path: process,
+            };
+            trace(&rules, &query)
+          }
+        };
+        serde_json::to_value(trace).map_err(|err| err.to_string())
+      }
+    }
+  }
+
+  fn refresh(&self) {
+    let _ = self.events.send(WindowEvent::Refresh);
+  }
+}
+
+/// `resumes_in` is in seconds, `null` unless it is a timed pause.
+fn pause_status() -> Value {
+  json!({
+    "paused": Pause::is_paused(),
+    "resumes_in": Pause::remaining().map(|remaining| remaining.as_secs()),
+  })
+}
+
+/// Starts accepting connections in the background. Only one cute-borders
+/// can listen at a time, any other one logs it and goes without.
+pub fn listen(events: Sender<WindowEvent>) {
+  if let Err(err) = transport::listen(events) {
+    Logger::log("[ERROR] Failed to start listening for IPC connections");
+    Logger::log(&format!("[DEBUG] {:?}", err));
+  }
+}
+
+/// Sends one request to the running cute-borders and returns its response,
+/// `None` if none is running.
+pub fn send(request: &Value) -> io::Result<Option<Value>> {
+  match transport::connect()? {
+    Some((reader, writer)) => exchange(reader, writer, request).map(Some),
+    None => Ok(None),
+  }
+}
+
+fn exchange(reader: impl Read, mut writer: impl Write, request: &Value) -> io::Result<Value> {
+  writeln!(writer, "{}", request)?;
+  writer.flush()?;
+  let mut line = String::new();
+  BufReader::new(reader).read_line(&mut line)?;
+  serde_json::from_str(&line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn serve_client(reader: impl Read, writer: impl Write, events: Sender<WindowEvent>) {
+  let backend = PlatformBackend::default();
+  let handler = Handler::new(&backend, events);
+  if let Err(err) = handler.serve(BufReader::new(reader), writer) {
+    // Usually just a client that went away without reading its response.
+    Logger::log(&format!("[DEBUG] IPC connection closed: {:?}", err));
+  }
+}
+
+#[cfg(windows)]
+mod transport {
+  use std::ffi::OsStr;
+  use std::fs::{File, OpenOptions};
+  use std::io;
+  use std::iter::once;
+  use std::os::windows::ffi::OsStrExt;
+  use std::os::windows::io::{AsRawHandle, FromRawHandle};
+  use std::sync::mpsc::Sender;
+  use std::thread;
+  use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+  use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+  use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+  use winapi::um::winbase::{
+    FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX, PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+  };
+
+  use super::serve_client;
+  use crate::backend::WindowEvent;
+  use crate::logger::Logger;
+
+  /// `\\.\pipe\cute-borders-<user>`
+  fn path() -> String {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    format!(r"\\.\pipe\cute-borders-{}", user)
+  }
+
+  // The first instance is created with FILE_FLAG_FIRST_PIPE_INSTANCE, so it
+  // fails if another cute-borders already owns the name.
+  fn create_pipe(first: bool) -> io::Result<File> {
+    let name: Vec<u16> = OsStr::new(&path()).encode_wide().chain(once(0)).collect();
+    let mut open_mode = PIPE_ACCESS_DUPLEX;
+    if first {
+      open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let handle = unsafe {
+      CreateNamedPipeW(
+        name.as_ptr(),
+        open_mode,
+        PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_UNLIMITED_INSTANCES,
+        4096,
+        4096,
+        0,
+        std::ptr::null_mut(),
+      )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_handle(handle as _) })
+  }
+
+  fn accept(pipe: &File) -> io::Result<()> {
+    if unsafe { ConnectNamedPipe(pipe.as_raw_handle() as _, std::ptr::null_mut()) } != 0 {
+      return Ok(());
+    }
+    // The client connected between creating the pipe and this call.
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+      Some(code) if code == ERROR_PIPE_CONNECTED as i32 => Ok(()),
+      _ => Err(err),
+    }
+  }
+
+  pub fn listen(events: Sender<WindowEvent>) -> io::Result<()> {
+    let mut pipe = create_pipe(true)?;
+    thread::spawn(move || loop {
+      match accept(&pipe).and_then(|_| pipe.try_clone()) {
+        Ok(reader) => {
+          let events = events.clone();
+          thread::spawn(move || {
+            serve_client(reader, &pipe, events);
+            // Makes sure the client got everything before the pipe closes.
+            let _ = pipe.sync_all();
+          });
+        }
+        Err(err) => {
+          Logger::log("[ERROR] Failed to accept IPC connection");
+          Logger::log(&format!("[DEBUG] {:?}", err));
+        }
+      }
+      pipe = match create_pipe(false) {
+        Ok(pipe) => pipe,
+        Err(err) => {
+          Logger::log("[ERROR] Failed to create IPC pipe, no longer listening");
+          Logger::log(&format!("[DEBUG] {:?}", err));
+          return;
+        }
+      };
+    });
+    Ok(())
+  }
+
+  /// A reader and writer for the pipe, `None` if no cute-borders is
+  /// listening.
+  pub fn connect() -> io::Result<Option<(File, File)>> {
+    match OpenOptions::new().read(true).write(true).open(path()) {
+      Ok(pipe) => Ok(Some((pipe.try_clone()?, pipe))),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+      Err(err) => Err(err),
+    }
+  }
+}
+
+#[cfg(not(windows))]
+mod transport {
+  use std::fs;
+  use std::io;
+  use std::os::unix::fs::PermissionsExt;
+  use std::os::unix::net::{UnixListener, UnixStream};
+  use std::sync::mpsc::Sender;
+  use std::thread;
+
+  use super::serve_client;
+  use crate::backend::WindowEvent;
+  use crate::logger::Logger;
+  use crate::util::get_file_path;
+
+  /// `~/.cuteborders/ipc.sock`
+  fn path() -> String {
+    get_file_path("ipc.sock")
+  }
+
+  pub fn listen(events: Sender<WindowEvent>) -> io::Result<()> {
+    listen_at(&path(), events)
+  }
+
+  pub fn listen_at(path: &str, events: Sender<WindowEvent>) -> io::Result<()> {
+    if UnixStream::connect(path).is_ok() {
+      return Err(io::Error::new(
+        io::ErrorKind::AddrInUse,
+        "another cute-borders is already listening",
+      ));
+    }
+    // Left over from a previous run that didn't exit cleanly.
+    let _ = fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    thread::spawn(move || {
+      for stream in listener.incoming() {
+        match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+          Ok((reader, writer)) => {
+            let events = events.clone();
+            thread::spawn(move || serve_client(reader, writer, events));
+          }
+          Err(err) => {
+            Logger::log("[ERROR] Failed to accept IPC connection");
+            Logger::log(&format!("[DEBUG] {:?}", err));
+          }
+        }
+      }
+    });
+    Ok(())
+  }
+
+  /// A reader and writer for the socket, `None` if no cute-borders is
+  /// listening.
+  pub fn connect() -> io::Result<Option<(UnixStream, UnixStream)>> {
+    connect_at(&path())
+  }
+
+  pub fn connect_at(path: &str) -> io::Result<Option<(UnixStream, UnixStream)>> {
+    match UnixStream::connect(path) {
+      Ok(stream) => Ok(Some((stream.try_clone()?, stream))),
+      // A socket file left over from a run that didn't exit cleanly refuses.
+      Err(err)
+        if matches!(
+          err.kind(),
+          io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+        ) =>
+      {
+        Ok(None)
+      }
+      Err(err) => Err(err),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+  use std::io::Cursor;
+  use std::sync::mpsc::{channel, Receiver};
+
+  use super::*;
+  use crate::backend::FakeBackend;
+  use crate::color::Color;
+  use crate::state::State;
+  use crate::testing::{self, window};
+  use crate::util::get_file_path;
+
+  const CONFIG: &str = r##"
+colors:
+  pink: "#ff00ff"
+window_rules:
+  - match: "Global"
+    active_border_color: "#ff0000"
+    inactive_border_color: "#0000ff"
+  - match: "Class"
+    contains: "MozillaWindowClass"
+    active_border_color: "$pink"
+"##;
+
+  const PROFILES: &str = r##"
+window_rules:
+  - match: "Global"
+    active_border_color: "#ff0000"
+profiles:
+  work:
+    window_rules:
+      - match: "Global"
+        active_border_color: "#00ff00"
+"##;
+
+  fn backend() -> FakeBackend {
+    let backend = FakeBackend::default();
+    backend.add_window(window(1, 10, "Mozilla Firefox", "MozillaWindowClass"));
+    backend.add_window(window(2, 20, "Untitled - Notepad", "Notepad"));
+    backend.set_foreground(Some(1));
+    backend
+  }
+
+  /// Sends `requests` over one connection, one response per request.
+  fn send(backend: &FakeBackend, requests: &[&str]) -> (Vec<Value>, Receiver<WindowEvent>) {
+    let (sender, receiver) = channel();
+    let input = format!("{}\n", requests.join("\n"));
+    let mut output = Vec::new();
+    Handler::new(backend, sender)
+      .serve(Cursor::new(input), &mut output)
+      .unwrap();
+    let responses = String::from_utf8(output)
+      .unwrap()
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+    (responses, receiver)
+  }
+
+  fn refreshes(receiver: &Receiver<WindowEvent>) -> usize {
+    receiver
+      .try_iter()
+      .filter(|event| *event == WindowEvent::Refresh)
+      .count()
+  }
+
+  #[test]
+  fn status() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let (responses, _) = send(&backend(), &[r#"{"command": "status"}"#]);
+    let status = &responses[0];
+    assert_eq!(status["ok"], json!(true));
+    assert_eq!(status["version"], json!(env!("CARGO_PKG_VERSION")));
+    assert_eq!(status["paused"], json!(false));
+    assert_eq!(status["overrides"], json!(0));
+    assert_eq!(status["config_errors"], json!([]));
+    assert!(status["cache"]["applied"].is_u64());
+  }
+
+  #[test]
+  fn invalid_requests() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let (responses, receiver) = send(
+      &backend(),
+      &[
+        "not json",
+        "",
+        r#"{"command": "recolor"}"#,
+        r#"{"command": "pause", "hours": 1}"#,
+      ],
+    );
+    // Blank lines are skipped, everything else gets an answer.
+    assert_eq!(responses.len(), 3);
+    for response in &responses {
+      assert_eq!(response["ok"], json!(false));
+      assert!(response["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Invalid request"));
+    }
+    assert_eq!(refreshes(&receiver), 0);
+  }
+
+  #[test]
+  fn overrides_use_the_palette() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    let (responses, receiver) = send(
+      &backend,
+      &[
+        r#"{"command": "override", "hwnd": 2, "color": "$pink"}"#,
+        r#"{"command": "override", "hwnd": 1, "color": "$nope"}"#,
+        r#"{"command": "override", "hwnd": 3, "color": "red"}"#,
+      ],
+    );
+    assert_eq!(responses[0], json!({ "ok": true }));
+    assert_eq!(Overrides::get(2), Some(Color::Rgb(255, 0, 255)));
+    assert_eq!(responses[1]["ok"], json!(false));
+    assert_eq!(Overrides::get(1), None);
+    assert_eq!(
+      responses[2],
+      json!({ "ok": false, "error": "No window 0x3" })
+    );
+    assert_eq!(refreshes(&receiver), 1);
+
+    let (responses, _) = send(&backend, &[r#"{"command": "override", "hwnd": 2}"#]);
+    assert_eq!(responses[0], json!({ "ok": true }));
+    assert_eq!(Overrides::get(2), None);
+  }
+
+  #[test]
+  fn pause_and_resume() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let (responses, receiver) = send(
+      &backend(),
+      &[
+        r#"{"command": "pause", "minutes": 15}"#,
+        r#"{"command": "resume"}"#,
+      ],
+    );
+    assert_eq!(responses[0]["paused"], json!(true));
+    assert!(responses[0]["resumes_in"].as_u64().unwrap() > 14 * 60);
+    assert_eq!(
+      responses[1],
+      json!({ "ok": true, "paused": false, "resumes_in": null })
+    );
+    assert_eq!(refreshes(&receiver), 2);
+    assert!(!Pause::is_paused());
+  }
+
+  #[test]
+  fn list_windows_and_explain() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let (responses, _) = send(
+      &backend(),
+      &[
+        r#"{"command": "list-windows"}"#,
+        r#"{"command": "explain", "hwnd": 1}"#,
+        r#"{"command": "explain", "title": "Notepad"}"#,
+        r#"{"command": "explain", "hwnd": 3}"#,
+      ],
+    );
+    let windows = responses[0]["windows"].as_array().unwrap();
+    assert_eq!(windows.len(), 2);
+    assert_eq!(windows[0]["hwnd"], json!(1));
+    assert_eq!(windows[0]["focused"], json!(true));
+
+    assert_eq!(responses[1]["ok"], json!(true));
+    let steps = responses[1]["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0]["outcome"], json!("matched"));
+    assert_eq!(responses[2]["steps"][0]["outcome"], json!("not_matched"));
+    assert_eq!(
+      responses[3],
+      json!({ "ok": false, "error": "No window 0x3" })
+    );
+  }
+
+  /// Border of the first rule of the running config, the profile's if one is active.
+  fn active_border() -> Option<Color> {
+    Config::get().window_rules[0].style(true).border_color
+  }
+
+  #[test]
+  fn set_profile_is_remembered() {
+    let _lock = testing::lock();
+    fs::write(get_file_path("config.yaml"), PROFILES).unwrap();
+    assert!(Config::reload());
+    let (responses, receiver) = send(
+      &backend(),
+      &[
+        r#"{"command": "set-profile", "profile": "work"}"#,
+        r#"{"command": "set-profile", "profile": "home"}"#,
+      ],
+    );
+    assert_eq!(responses[0], json!({ "ok": true, "profile": "work" }));
+    assert_eq!(
+      responses[1],
+      json!({ "ok": false, "error": "Unknown profile `home`" })
+    );
+    assert_eq!(refreshes(&receiver), 1);
+    assert_eq!(active_border(), Some(Color::Rgb(0, 255, 0)));
+    assert_eq!(
+      State::load().unwrap().active_profile.as_deref(),
+      Some("work")
+    );
+
+    let (responses, _) = send(&backend(), &[r#"{"command": "set-profile"}"#]);
+    assert_eq!(responses[0], json!({ "ok": true, "profile": null }));
+    assert_eq!(active_border(), Some(Color::Rgb(255, 0, 0)));
+    assert_eq!(State::load().unwrap().active_profile, None);
+  }
+
+  #[test]
+  fn reload_keeps_the_old_config_if_the_new_one_is_broken() {
+    let _lock = testing::lock();
+    let path = get_file_path("config.yaml");
+    fs::write(&path, PROFILES).unwrap();
+    assert!(Config::reload());
+
+    fs::write(&path, PROFILES.replace("#ff0000", "#0000ff")).unwrap();
+    let (responses, receiver) = send(&backend(), &[r#"{"command": "reload"}"#]);
+    assert_eq!(responses[0], json!({ "ok": true }));
+    assert_eq!(refreshes(&receiver), 1);
+    assert_eq!(active_border(), Some(Color::Rgb(0, 0, 255)));
+
+    fs::write(&path, "window_rules: [\n").unwrap();
+    let (responses, receiver) = send(&backend(), &[r#"{"command": "reload"}"#]);
+    assert_eq!(
+      responses[0],
+      json!({ "ok": false, "error": "Failed to reload the config, see log.txt" })
+    );
+    assert_eq!(refreshes(&receiver), 0);
+    assert_eq!(active_border(), Some(Color::Rgb(0, 0, 255)));
+  }
+
+  #[cfg(not(windows))]
+  #[test]
+  fn unix_socket_loopback() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let path = testing::home().join("loopback.sock");
+    let path = path.to_str().unwrap();
+    let (sender, receiver) = channel();
+    transport::listen_at(path, sender.clone()).unwrap();
+    let mode = fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // A second cute-borders doesn't take over the socket.
+    assert_eq!(
+      transport::listen_at(path, sender).unwrap_err().kind(),
+      io::ErrorKind::AddrInUse
+    );
+
+    let (reader, writer) = transport::connect_at(path).unwrap().unwrap();
+    let response = exchange(reader, writer, &json!({ "command": "pause" })).unwrap();
+    assert_eq!(response["paused"], json!(true));
+    assert_eq!(refreshes(&receiver), 1);
+    assert!(Pause::is_paused());
+
+    let missing = testing::home().join("missing.sock");
+    assert!(transport::connect_at(missing.to_str().unwrap())
+      .unwrap()
+      .is_none());
+  }
+}
@@ -1,305 +1,419 @@
 #![windows_subsystem = "windows"]
 #![allow(unused_assignments)]
 
-use check_elevation::is_elevated;
+use backend::EventSource;
+use backend::Hwnd;
+use backend::PlatformBackend;
+use backend::PlatformEventSource;
+use backend::WindowBackend;
+use backend::WindowInfo;
+use cache::ColorCache;
+use color::Color;
 use config::Config;
-use config::RuleMatch;
 use logger::Logger;
-use rainbow::Rainbow;
-use std::ffi::c_ulong;
-use std::ffi::OsStr;
-use std::ffi::OsString;
-use std::os::windows::ffi::OsStrExt;
-use std::os::windows::prelude::OsStringExt;
-use std::time::Duration;
-use tray_icon::menu::Menu;
-use tray_icon::menu::MenuEvent;
-use tray_icon::menu::MenuId;
-use tray_icon::menu::MenuItemBuilder;
-use tray_icon::Icon;
-use tray_icon::TrayIconBuilder;
-use util::get_exe_path;
-use util::get_file_path;
-use util::hex_to_colorref;
-use util::set_startup;
-use winapi::ctypes::c_int;
-use winapi::ctypes::c_void;
-use winapi::shared::minwindef::{BOOL, LPARAM};
-use winapi::shared::windef::HWND;
-use winapi::um::dwmapi::DwmSetWindowAttribute;
-use winapi::um::shellapi::ShellExecuteExW;
-use winapi::um::shellapi::SEE_MASK_NOASYNC;
-use winapi::um::shellapi::SEE_MASK_NOCLOSEPROCESS;
-use winapi::um::shellapi::SHELLEXECUTEINFOW;
-use winapi::um::winuser::EnumWindows;
-use winapi::um::winuser::GetClassNameW;
-use winapi::um::winuser::GetWindowTextLengthW;
-use winapi::um::winuser::GetWindowTextW;
-use winapi::um::winuser::WS_EX_TOOLWINDOW;
-use winapi::um::winuser::{
-  DispatchMessageW, GetForegroundWindow, GetMessageW, IsWindowVisible, TranslateMessage,
-  GWL_EXSTYLE,
-};
+use overrides::Overrides;
+use process::ProcessCache;
+use rules::resolve;
+use rules::LiveWindow;
+use scheduler::Scheduler;
+use std::process::ExitCode;
 
+const DWMWA_USE_IMMERSIVE_DARK_MODE: u32 = 20;
+const DWMWA_WINDOW_CORNER_PREFERENCE: u32 = 33;
 const DWMWA_BORDER_COLOR: u32 = 34;
+const DWMWA_CAPTION_COLOR: u32 = 35;
+const DWMWA_TEXT_COLOR: u32 = 36;
+const DWMWA_SYSTEMBACKDROP_TYPE: u32 = 38;
 const DWMWA_COLOR_DEFAULT: u32 = 0xFFFFFFFF;
 const DWMWA_COLOR_NONE: u32 = 0xFFFFFFFE;
-const COLOR_INVALID: u32 = 0x000000FF;
 
+/// What Windows uses for each attribute from `get_attributes_for_window`
+/// when nothing set it: default colors, DWMWCP_DEFAULT, DWMSBT_AUTO and
+/// light mode.
+const DWMWA_DEFAULTS: [(u32, u32); 6] = [
+  (DWMWA_BORDER_COLOR, DWMWA_COLOR_DEFAULT),
+  (DWMWA_CAPTION_COLOR, DWMWA_COLOR_DEFAULT),
+  (DWMWA_TEXT_COLOR, DWMWA_COLOR_DEFAULT),
+  (DWMWA_WINDOW_CORNER_PREFERENCE, 0),
+  (DWMWA_SYSTEMBACKDROP_TYPE, 0),
+  (DWMWA_USE_IMMERSIVE_DARK_MODE, 0),
+];
+
+mod backend;
+mod cache;
+mod cli;
+mod color;
 mod config;
+mod hotkey;
+mod include;
+mod inventory;
+mod ipc;
 mod logger;
+mod matcher;
+mod migrate;
+// Only the tray creates rules for now.
+#[cfg_attr(not(windows), allow(dead_code))]
+mod new_rule;
+mod notify;
+mod overrides;
+mod palette;
+mod palette_file;
+mod pause;
+mod process;
 mod rainbow;
+mod rules;
+mod scheduler;
+mod schema;
+#[cfg(windows)]
+mod startup;
+mod state;
+#[cfg(test)]
+mod testing;
+#[cfg(windows)]
+mod tray;
 mod util;
+mod watcher;
 
-fn main() {
-  if let Err(err) = set_startup(true) {
-    Logger::log("[ERROR] Failed to create or update startup task");
-    Logger::log(&format!("[DEBUG] {:?}", err));
+fn main() -> ExitCode {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let command = match cli::parse(&args) {
+    Ok(command) => command,
+    Err(err) => {
+      attach_console();
+      eprintln!("{}\n\n{}", err, cli::USAGE);
+      return ExitCode::from(cli::EXIT_USAGE);
+    }
+  };
+  if command == cli::Command::Run {
+    run();
   }
 
-  // I will just fucking update everything every 100ms
-  // I might want to do this properly buuuuut I dont even use this myself.
-  std::thread::spawn(|| loop {
-    Rainbow::tick(Config::get().rainbow_speed.unwrap_or(1.0));
-    apply_colors(false);
-    std::thread::sleep(Duration::from_millis(100));
-  });
+  attach_console();
+  let backend = PlatformBackend::default();
+  let result = cli::execute(&command, &backend, &mut std::io::stdout());
+  if let Err(err) = &result {
+    eprintln!("{}", err);
+  }
+  cli::exit_code(&result)
+}
 
-  let is_elevated = is_elevated().unwrap_or(false);
+// This is a GUI executable, so it doesn't get a console of its own.
+// Output from commands goes to the one it was started from, if any.
+fn attach_console() {
+  #[cfg(windows)]
   unsafe {
-    #[allow(unused_variables)]
-    let tray_icon; // needs to be in the main scope
-    if !Config::get().hide_tray_icon.unwrap_or(false) {
-      let tray_menu_builder = Menu::with_items(&[
-        &MenuItemBuilder::new()
-          .text("Open config")
-          .enabled(true)
-          .id(MenuId::new("0"))
-          .build(),
-        &MenuItemBuilder::new()
-          .text("Reload config")
-          .enabled(true)
-          .id(MenuId::new("1"))
-          .build(),
-        &MenuItemBuilder::new()
-          .text(if is_elevated { "Uninstall" } else { "Install" })
-          .enabled(true)
-          .id(MenuId::new("2"))
-          .build(),
-        &MenuItemBuilder::new()
-          .text("Exit")
-          .enabled(true)
-          .id(MenuId::new("3"))
-          .build(),
-      ]);
-
-      let tray_menu = match tray_menu_builder {
-        Ok(tray_menu) => tray_menu,
-        Err(err) => {
-          Logger::log("[ERROR] Failed to build tray icon");
-          Logger::log(&format!("[DEBUG] {:?}", err));
-          std::process::exit(1);
-        }
-      };
-
-      let icon = match Icon::from_resource(1, Some((64, 64))) {
-        Ok(icon) => icon,
-        Err(err) => {
-          Logger::log("[ERROR] Failed to create icon");
-          Logger::log(&format!("[DEBUG] {:?}", err));
-          std::process::exit(1);
-        }
-      };
-
-      let tray_icon_builder = TrayIconBuilder::new()
-        .with_menu(Box::new(tray_menu))
-        .with_menu_on_left_click(true)
-        .with_icon(icon)
-        .with_tooltip(format!("cute-borders v{}", env!("CARGO_PKG_VERSION")));
-
-      tray_icon = match tray_icon_builder.build() {
-        Ok(tray_icon) => tray_icon,
-        Err(err) => {
-          Logger::log("[ERROR] Failed to build tray icon");
-          Logger::log(&format!("[DEBUG] {:?}", err));
-          std::process::exit(1);
-        }
-      };
-
-      MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
-        if event.id == MenuId::new("0") {
-          let _ = open::that(get_file_path("config.yaml"));
-        } else if event.id == MenuId::new("1") {
-          Config::reload();
-          apply_colors(false);
-        } else if event.id == MenuId::new("2") {
-          if is_elevated {
-            if let Err(err) = set_startup(false) {
-              Logger::log("[ERROR] Failed to create or update startup task");
-              Logger::log(&format!("[DEBUG] {:?}", err));
-            }
-            apply_colors(true);
-            std::process::exit(0);
-          } else {
-            let lp_verb: Vec<u16> = OsStr::new("runas")
-              .encode_wide()
-              .chain(std::iter::once(0))
-              .collect();
-            let d = get_exe_path();
-            let v = d.to_str().unwrap_or_default();
-            let lp_file: Vec<u16> = OsStr::new(&v)
-              .encode_wide()
-              .chain(std::iter::once(0))
-              .collect();
-            let lp_par: Vec<u16> = OsStr::new("")
-              .encode_wide()
-              .chain(std::iter::once(0))
-              .collect();
-
-            let mut sei = SHELLEXECUTEINFOW {
-              cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
-              fMask: SEE_MASK_NOASYNC | SEE_MASK_NOCLOSEPROCESS,
-              lpVerb: lp_verb.as_ptr(),
-              lpFile: lp_file.as_ptr(),
-              lpParameters: lp_par.as_ptr(),
-              nShow: 1,
-              dwHotKey: 0,
-              hInstApp: std::ptr::null_mut(),
-              hMonitor: std::ptr::null_mut(),
-              hProcess: std::ptr::null_mut(),
-              hkeyClass: std::ptr::null_mut(),
-              hwnd: std::ptr::null_mut(),
-              lpClass: std::ptr::null_mut(),
-              lpDirectory: std::ptr::null_mut(),
-              lpIDList: std::ptr::null_mut(),
-            };
-
-            ShellExecuteExW(&mut sei);
-            apply_colors(true);
-            std::process::exit(0);
-          }
-        } else if event.id == MenuId::new("3") {
-          apply_colors(true);
-          std::process::exit(0);
-        }
-      }));
-    }
+    winapi::um::wincon::AttachConsole(winapi::um::wincon::ATTACH_PARENT_PROCESS);
+  }
+}
+
+fn run() -> ! {
+  schema::write_schema_file();
+  let events = PlatformEventSource::new();
+  watcher::watch(events.sender());
+  ipc::listen(events.sender());
 
-    let mut msg = std::mem::zeroed();
-    while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) != 0 {
-      TranslateMessage(&msg);
-      DispatchMessageW(&msg);
+  #[cfg(windows)]
+  {
+    if let Err(err) = startup::set_startup(true) {
+      Logger::log("[ERROR] Failed to create or update startup task");
+      Logger::log(&format!("[DEBUG] {:?}", err));
     }
 
-    apply_colors(true);
+    let sender = events.sender();
+    std::thread::spawn(move || update_loop(events));
+    tray::run(sender);
+    std::process::exit(0);
+  }
+
+  // No tray off-Windows, just run the engine against the fake backend.
+  #[cfg(not(windows))]
+  {
+    update_loop(events);
+    std::process::exit(0);
   }
 }
 
-unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
-  if IsWindowVisible(hwnd) != 0 {
-    let mut title_buffer: [u16; 512] = [0; 512];
-    let text_length = GetWindowTextLengthW(hwnd) + 1;
-    if text_length > 0 {
-      GetWindowTextW(hwnd, title_buffer.as_mut_ptr(), text_length as c_int);
-    }
-    let title = OsString::from_wide(&title_buffer)
-      .to_string_lossy()
-      .into_owned();
-
-    let ex_style = winapi::um::winuser::GetWindowLongW(hwnd, GWL_EXSTYLE) as c_int;
-
-    let mut class_buffer: [u16; 256] = [0; 256];
-    let class_result = GetClassNameW(hwnd, class_buffer.as_mut_ptr(), class_buffer.len() as c_int);
-    let mut class_name = String::new();
-    if class_result > 0 {
-      class_name = OsString::from_wide(&class_buffer)
-        .to_string_lossy()
-        .into_owned();
-    }
+fn update_loop(events: PlatformEventSource) {
+  let backend = PlatformBackend::default();
+  Scheduler::new(&backend, &events).run();
+}
+
+/// DWM attributes and the values they should have right now.
+/// `None` leaves the attribute alone, or restores it if it was set before.
+fn get_attributes_for_window(
+  backend: &dyn WindowBackend,
+  window: &WindowInfo,
+  is_active: bool,
+) -> [(u32, Option<u32>); 6] {
+  let config = Config::get();
+  let resolved = resolve(&config.window_rules, &LiveWindow::new(backend, window));
+  let style = if is_active {
+    resolved.active
+  } else {
+    resolved.inactive
+  };
+  let color = |color: Option<Color>| color.map(|color| color.to_colorref(backend));
+  let border_color = Overrides::get(window.hwnd)
+    .or(style.border_color)
+    .unwrap_or(Color::Default);
 
-    // Exclude certain window styles like WS_EX_TOOLWINDOW
-    if ex_style & (WS_EX_TOOLWINDOW as i32) == 0 {
-      let visible_windows: &mut Vec<(HWND, String, String)> =
-        &mut *(lparam as *mut Vec<(HWND, String, String)>);
-      visible_windows.push((hwnd, title, class_name));
+  [
+    (DWMWA_BORDER_COLOR, Some(border_color.to_colorref(backend))),
+    (DWMWA_CAPTION_COLOR, color(style.caption_color)),
+    (DWMWA_TEXT_COLOR, color(style.text_color)),
+    (
+      DWMWA_WINDOW_CORNER_PREFERENCE,
+      style.corner_preference.map(|corner| corner.value()),
+    ),
+    (
+      DWMWA_SYSTEMBACKDROP_TYPE,
+      style.backdrop.map(|backdrop| backdrop.value()),
+    ),
+    (
+      DWMWA_USE_IMMERSIVE_DARK_MODE,
+      style.dark_mode.map(u32::from),
+    ),
+  ]
+}
+
+fn apply_colors(backend: &dyn WindowBackend, reset: bool) {
+  let active = backend.foreground_window();
+  let windows = backend.visible_windows();
+
+  // Destroy events can be missed and handles get reused. Hidden windows
+  // still exist and keep their cache and override for when they come back.
+  let cached = [
+    ColorCache::hwnds(),
+    Overrides::hwnds(),
+    ProcessCache::hwnds(),
+  ];
+  for hwnd in cached.into_iter().flatten() {
+    if !backend.is_window(hwnd) {
+      forget_window(hwnd);
     }
   }
 
-  1
-}
+  for window in windows {
+    apply_colors_to_window(backend, &window, active, reset);
+  }
 
-fn get_colors_for_window(_hwnd: HWND, title: String, class: String, reset: bool) -> (u32, u32) {
   if reset {
-    return (DWMWA_COLOR_DEFAULT, DWMWA_COLOR_DEFAULT);
+    let stats = ColorCache::stats();
+    Logger::log(&format!(
+      "[DEBUG] DwmSetWindowAttribute calls: {} applied, {} skipped, {} windows evicted",
+      stats.applied, stats.skipped, stats.evicted
+    ));
   }
+}
 
-  let config = Config::get();
-  let mut color_active = COLOR_INVALID;
-  let mut color_inactive = COLOR_INVALID;
-
-  for rule in config.window_rules.iter() {
-    match rule.rule_match {
-      RuleMatch::Global => {
-        color_active = hex_to_colorref(&rule.active_border_color);
-        color_inactive = hex_to_colorref(&rule.inactive_border_color);
-      }
-      RuleMatch::Title => {
-        if let Some(contains_str) = &rule.contains {
-          if title.to_lowercase().contains(&contains_str.to_lowercase()) {
-            color_active = hex_to_colorref(&rule.active_border_color);
-            color_inactive = hex_to_colorref(&rule.inactive_border_color);
-            break;
-          }
-        } else {
-          Logger::log("Expected `contains` on `Match=\"Title\"`");
-        }
-      }
-      RuleMatch::Class => {
-        if let Some(contains_str) = &rule.contains {
-          if class.to_lowercase().contains(&contains_str.to_lowercase()) {
-            color_active = hex_to_colorref(&rule.active_border_color);
-            color_inactive = hex_to_colorref(&rule.inactive_border_color);
-            break;
-          }
-        } else {
-          Logger::log("Expected `contains` on `Match=\"Class\"`");
-        }
-      }
-    }
+/// Drops everything kept for `hwnd` once it is destroyed.
+fn forget_window(hwnd: Hwnd) {
+  ColorCache::evict(hwnd);
+  Overrides::remove(hwnd);
+  ProcessCache::forget(hwnd);
+}
+
+fn apply_colors_to_window(
+  backend: &dyn WindowBackend,
+  window: &WindowInfo,
+  active: Option<Hwnd>,
+  reset: bool,
+) {
+  // Exclude certain window styles like WS_EX_TOOLWINDOW
+  if window.is_tool_window() {
+    return;
+  }
+
+  if reset {
+    ColorCache::reset(backend, window.hwnd);
+    return;
   }
 
-  (color_active, color_inactive)
+  let is_active = active == Some(window.hwnd);
+  for (attribute, value) in get_attributes_for_window(backend, window, is_active) {
+    ColorCache::set_attribute(backend, window.hwnd, attribute, value);
+  }
 }
 
-fn apply_colors(reset: bool) {
-  let mut visible_windows: Vec<(HWND, String, String)> = Vec::new();
-  unsafe {
-    EnumWindows(
-      Some(enum_windows_callback),
-      &mut visible_windows as *mut _ as LPARAM,
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::{FakeBackend, WS_EX_TOOLWINDOW};
+  use crate::testing::{self, window};
+
+  const CONFIG: &str = r##"
+window_rules:
+  - match: "Global"
+    active_border_color: "#ff0000"
+    inactive_border_color: "#0000ff"
+  - match: "Class"
+    contains: "MozillaWindowClass"
+    active_border_color: "accent"
+    active_caption_color: "#00ff00"
+  - match: "Process"
+    contains: "code.exe"
+    match_mode: "exact"
+    active_border_color: "#00ffff"
+"##;
+
+  // Two browser windows, the first one focused.
+  fn backend() -> FakeBackend {
+    let backend = FakeBackend::default();
+    backend.add_window(window(1, 10, "Mozilla Firefox", "MozillaWindowClass"));
+    backend.add_window(window(2, 10, "Mozilla Firefox", "MozillaWindowClass"));
+    backend.set_foreground(Some(1));
+    backend
+  }
+
+  #[test]
+  fn colors_focused_and_unfocused_windows() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    backend.set_accent_color(Some(0x00123456));
+
+    apply_colors(&backend, false);
+
+    assert_eq!(backend.attribute(1, DWMWA_BORDER_COLOR), Some(0x00123456));
+    assert_eq!(backend.attribute(1, DWMWA_CAPTION_COLOR), Some(0x0000ff00));
+    assert_eq!(backend.attribute(2, DWMWA_BORDER_COLOR), Some(0x00ff0000));
+    // Nothing sets it for unfocused windows and it was never changed.
+    assert_eq!(backend.attribute(2, DWMWA_CAPTION_COLOR), None);
+  }
+
+  #[test]
+  fn focus_change_swaps_colors() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    apply_colors(&backend, false);
+
+    backend.set_foreground(Some(2));
+    apply_colors(&backend, false);
+
+    assert_eq!(backend.attribute(1, DWMWA_BORDER_COLOR), Some(0x00ff0000));
+    // The caption the focused color set goes back to the default.
+    assert_eq!(
+      backend.attribute(1, DWMWA_CAPTION_COLOR),
+      Some(DWMWA_COLOR_DEFAULT)
+    );
+    // No accent color falls back to the default border.
+    assert_eq!(
+      backend.attribute(2, DWMWA_BORDER_COLOR),
+      Some(DWMWA_COLOR_DEFAULT)
+    );
+  }
+
+  #[test]
+  fn unchanged_windows_are_not_set_again() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+
+    apply_colors(&backend, false);
+    let calls = backend.attribute_calls();
+    assert!(calls > 0);
+    apply_colors(&backend, false);
+    assert_eq!(backend.attribute_calls(), calls);
+
+    // A new window only costs the attributes a rule sets for it.
+    backend.add_window(window(3, 11, "Untitled", "Notepad"));
+    apply_colors_to_window(
+      &backend,
+      &window(3, 11, "Untitled", "Notepad"),
+      Some(1),
+      false,
     );
+    assert_eq!(backend.attribute_calls(), calls + 1);
+    assert_eq!(backend.attribute(3, DWMWA_BORDER_COLOR), Some(0x00ff0000));
   }
 
-  for (hwnd, title, class) in visible_windows {
-    let (color_active, color_inactive) = get_colors_for_window(hwnd, title, class, reset);
-    unsafe {
-      let active = GetForegroundWindow();
-
-      if active == hwnd {
-        DwmSetWindowAttribute(
-          hwnd,
-          DWMWA_BORDER_COLOR,
-          &color_active as *const _ as *const c_void,
-          std::mem::size_of::<c_ulong>() as u32,
-        );
-      } else {
-        DwmSetWindowAttribute(
-          hwnd,
-          DWMWA_BORDER_COLOR,
-          &color_inactive as *const _ as *const c_void,
-          std::mem::size_of::<c_ulong>() as u32,
-        );
-      }
+  #[test]
+  fn tool_windows_are_left_alone() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = FakeBackend::default();
+    let mut tool = window(1, 10, "Tooltip", "tooltips_class32");
+    tool.ex_style = WS_EX_TOOLWINDOW;
+    backend.add_window(tool);
+    backend.set_foreground(Some(1));
+
+    apply_colors(&backend, false);
+    assert_eq!(backend.attribute_calls(), 0);
+  }
+
+  #[test]
+  fn process_rules_use_the_executable_name() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = FakeBackend::default();
+    backend.add_window(window(1, 20, "main.rs", "Chrome_WidgetWin_1"));
+    backend.set_process_path(20, r"C:\Program Files\VS Code\Code.exe");
+    backend.set_foreground(Some(1));
+
+    apply_colors(&backend, false);
+    assert_eq!(backend.attribute(1, DWMWA_BORDER_COLOR), Some(0x00ffff00));
+  }
+
+  #[test]
+  fn reset_restores_defaults() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    apply_colors(&backend, false);
+
+    apply_colors(&backend, true);
+    for hwnd in [1, 2] {
+      assert_eq!(
+        backend.attribute(hwnd, DWMWA_BORDER_COLOR),
+        Some(DWMWA_COLOR_DEFAULT)
+      );
     }
+    assert_eq!(
+      backend.attribute(1, DWMWA_CAPTION_COLOR),
+      Some(DWMWA_COLOR_DEFAULT)
+    );
+  }
+
+  #[test]
+  fn closed_windows_are_forgotten() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    apply_colors(&backend, false);
+
+    // The handle gets reused by a new window, which has to be colored
+    // even though the cache had the same value for the old one.
+    backend.remove_window(2);
+    apply_colors(&backend, false);
+    let calls = backend.attribute_calls();
+    backend.add_window(window(2, 12, "Mozilla Firefox", "MozillaWindowClass"));
+    apply_colors(&backend, false);
+    assert_eq!(backend.attribute_calls(), calls + 1);
+    assert_eq!(backend.attribute(2, DWMWA_BORDER_COLOR), Some(0x00ff0000));
+  }
+
+  #[test]
+  fn hidden_windows_keep_their_cache_and_override() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    Overrides::set(2, Some(Color::Rgb(0, 0, 0)));
+    apply_colors(&backend, false);
+
+    backend.set_hidden(2, true);
+    apply_colors(&backend, false);
+    assert_eq!(Overrides::get(2), Some(Color::Rgb(0, 0, 0)));
+
+    // Nothing changed while it was hidden, so showing it costs no calls.
+    let calls = backend.attribute_calls();
+    backend.set_hidden(2, false);
+    apply_colors(&backend, false);
+    assert_eq!(backend.attribute_calls(), calls);
+    assert_eq!(backend.attribute(2, DWMWA_BORDER_COLOR), Some(0));
+
+    backend.remove_window(2);
+    apply_colors(&backend, false);
+    assert_eq!(Overrides::get(2), None);
   }
 }
@@ -0,0 +1,61 @@
+use regex::{Regex, RegexBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+  #[default]
+  Contains,
+  Exact,
+  Glob,
+  Regex,
+}
+
+/// A rule pattern compiled once when the config is loaded.
+/// Everything but `regex` ignores case, regexes can opt in with `(?i)`.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+  Contains(String),
+  Exact(String),
+  Regex(Regex),
+}
+
+impl Matcher {
+  pub fn new(pattern: &str, mode: MatchMode) -> Result<Self, regex::Error> {
+    let matcher = match mode {
+      MatchMode::Contains => Matcher::Contains(pattern.to_lowercase()),
+      MatchMode::Exact => Matcher::Exact(pattern.to_lowercase()),
+      MatchMode::Glob => Matcher::Regex(
+        RegexBuilder::new(&glob_to_regex(pattern))
+          .case_insensitive(true)
+          .build()?,
+      ),
+      MatchMode::Regex => Matcher::Regex(Regex::new(pattern)?),
+    };
+    Ok(matcher)
+  }
+
+  pub fn is_match(&self, text: &str) -> bool {
+    match self {
+      Matcher::Contains(pattern) => text.to_lowercase().contains(pattern),
+      Matcher::Exact(pattern) => text.to_lowercase() == *pattern,
+      Matcher::Regex(regex) => regex.is_match(text),
+    }
+  }
+}
+
+/// `*` matches any run of characters and `?` a single one,
+/// the glob has to match the whole text.
+fn glob_to_regex(glob: &str) -> String {
+  let mut regex = String::from("^");
+  for c in glob.chars() {
+    match c {
+      '*' => regex.push_str(".*"),
+      '?' => regex.push('.'),
+      c => regex.push_str(&regex::escape(&c.to_string())),
+    }
+  }
+  regex.push('$');
+  regex
+}
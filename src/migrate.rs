@@ -0,0 +1,308 @@
+use serde_yaml::{Mapping, Value};
+
+/// The config version this build writes and understands.
+pub const CURRENT_VERSION: u64 = 2;
+
+/// A top level key that moved into a section, e.g. `hide_tray_icon` to
+/// `tray.hide_icon`. Kept as data so the file can be edited as text and
+/// keep its comments.
+struct Move {
+  from: &'static str,
+  section: &'static str,
+  key: &'static str,
+}
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` config to version `n + 2`.
+const MIGRATIONS: &[&[Move]] = &[V1_TO_V2];
+
+const V1_TO_V2: &[Move] = &[
+  Move {
+    from: "hide_tray_icon",
+    section: "tray",
+    key: "hide_icon",
+  },
+  Move {
+    from: "rainbow_speed",
+    section: "rainbow",
+    key: "speed",
+  },
+];
+
+pub struct Migrated {
+  pub from: u64,
+  pub contents: String,
+  /// Whether `contents` is the original file with only the upgraded keys
+  /// changed. If it couldn't be edited that way `contents` lost every
+  /// comment, so it is only used in memory.
+  pub preserves_text: bool,
+}
+
+/// Upgrades an older config step by step until it is at `CURRENT_VERSION`.
+/// `None` if there is nothing to upgrade. Configs without `version` are
+/// version 1. Broken YAML is left for the actual parse to report.
+pub fn migrate(contents: &str) -> Result<Option<Migrated>, String> {
+  let Ok(mut config) = serde_yaml::from_str::<Value>(contents) else {
+    return Ok(None);
+  };
+  let Some(mapping) = config.as_mapping_mut() else {
+    return Ok(None);
+  };
+
+  let version = match mapping.get("version") {
+    Some(version) => version
+      .as_u64()
+      .ok_or_else(|| format!("`version` has to be a number, not {:?}", version))?,
+    None => 1,
+  };
+  if version == CURRENT_VERSION {
+    return Ok(None);
+  }
+  if version == 0 || version > CURRENT_VERSION {
+    return Err(format!(
+      "Unsupported config version {}, this cute-borders understands up to version {}",
+      version, CURRENT_VERSION
+    ));
+  }
+
+  let before = mapping.clone();
+  let moves: Vec<&Move> = MIGRATIONS[(version - 1) as usize..]
+    .iter()
+    .flat_map(|moves| moves.iter())
+    .collect();
+  for step in &moves {
+    move_key(mapping, step);
+  }
+  // e.g. a config that only lacks `version`, no need to rewrite it
+  if *mapping == before {
+    return Ok(None);
+  }
+  set_version(mapping);
+
+  // The text edit has to come out the same as the upgraded values,
+  // anything it can't handle falls back to the values without comments.
+  if let Some(text) = migrate_text(contents, &moves) {
+    if serde_yaml::from_str::<Value>(&text).ok().as_ref() == Some(&config) {
+      return Ok(Some(Migrated {
+        from: version,
+        contents: text,
+        preserves_text: true,
+      }));
+    }
+  }
+  let contents = serde_yaml::to_string(&config).map_err(|err| err.to_string())?;
+  Ok(Some(Migrated {
+    from: version,
+    contents,
+    preserves_text: false,
+  }))
+}
+
+/// Moves the top level `from` into `section.key`. The section takes the
+/// place of `from`, so the order of everything else stays the same.
+fn move_key(config: &mut Mapping, step: &Move) {
+  if !config.contains_key(step.from) {
+    return;
+  }
+
+  for (name, value) in std::mem::take(config) {
+    if name.as_str() != Some(step.from) {
+      config.insert(name, value);
+      continue;
+    }
+    let section = config.entry(step.section.into()).or_insert(Value::Null);
+    // `tray:` with nothing under it yet
+    if section.is_null() {
+      *section = Value::Mapping(Mapping::new());
+    }
+    if let Some(section) = section.as_mapping_mut() {
+      section.insert(step.key.into(), value);
+    }
+  }
+}
+
+/// `version` always goes first.
+fn set_version(config: &mut Mapping) {
+  let old = std::mem::take(config);
+  config.insert("version".into(), CURRENT_VERSION.into());
+  for (name, value) in old {
+    if name.as_str() != Some("version") {
+      config.insert(name, value);
+    }
+  }
+}
+
+/// Same as `move_key` and `set_version`, but on the text of the file so
+/// comments and formatting stay. `None` for layouts it doesn't know, like
+/// flow mappings.
+fn migrate_text(contents: &str, moves: &[&Move]) -> Option<String> {
+  let newline = if contents.contains("\r\n") {
+    "\r\n"
+  } else {
+    "\n"
+  };
+  let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+  for step in moves {
+    move_line(&mut lines, step)?;
+  }
+
+  let version = format!("version: {}", CURRENT_VERSION);
+  match top_level(&lines, "version") {
+    Some(index) => lines[index] = version,
+    // After leading comments like the yaml-language-server one.
+    None => {
+      let first_key = lines
+        .iter()
+        .position(|line| is_top_level_key(line))
+        .unwrap_or(lines.len());
+      lines.insert(first_key, version);
+    }
+  }
+
+  let mut text = lines.join(newline);
+  if contents.ends_with('\n') {
+    text.push_str(newline);
+  }
+  Some(text)
+}
+
+fn move_line(lines: &mut Vec<String>, step: &Move) -> Option<()> {
+  let Some(index) = top_level(lines, step.from) else {
+    return Some(());
+  };
+  // A value spread over more lines, e.g. a block scalar, moves with it.
+  let end = block_end(lines, index);
+  let value = lines[index][step.from.len() + 1..].to_string();
+  let mut moved = vec![format!("  {}:{}", step.key, value)];
+  moved.extend(
+    lines[index + 1..end]
+      .iter()
+      .map(|line| format!("  {}", line)),
+  );
+
+  match top_level(lines, step.section) {
+    None => {
+      moved.insert(0, format!("{}:", step.section));
+      lines.splice(index..end, moved);
+    }
+    Some(section) => {
+      // Only block mappings can take another key.
+      let inline = lines[section][step.section.len() + 1..]
+        .split(" #")
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+      if !inline.is_empty() {
+        return None;
+      }
+      let section_end = block_end(lines, section);
+      lines.drain(index..end);
+      let at = if section_end > index {
+        section_end - (end - index)
+      } else {
+        section_end
+      };
+      lines.splice(at..at, moved);
+    }
+  }
+  Some(())
+}
+
+/// Index of the line with the top level `key:`.
+fn top_level(lines: &[String], key: &str) -> Option<usize> {
+  lines.iter().position(|line| {
+    line
+      .strip_prefix(key)
+      .is_some_and(|rest| rest.starts_with(':'))
+  })
+}
+
+/// The line after the indented block that belongs to the key at `index`,
+/// without trailing blank lines and comments that aren't indented.
+fn block_end(lines: &[String], index: usize) -> usize {
+  let mut end = index + 1;
+  for (next, line) in lines.iter().enumerate().skip(index + 1) {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() {
+      continue;
+    }
+    if line.len() == trimmed.len() {
+      if trimmed.starts_with('#') {
+        continue;
+      }
+      break;
+    }
+    end = next + 1;
+  }
+  end
+}
+
+fn is_top_level_key(line: &str) -> bool {
+  !line.is_empty()
+    && !line.starts_with([' ', '\t', '#', '-'])
+    && !line.starts_with("---")
+    && !line.starts_with('%')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Golden files, `<name>.v1.yaml` is upgraded to `<name>.v2.yaml`.
+  const FIXTURES: &[(&str, &str, &str)] = &[
+    (
+      "default",
+      include_str!("../tests/fixtures/migrate/default.v1.yaml"),
+      include_str!("../tests/fixtures/migrate/default.v2.yaml"),
+    ),
+    (
+      "existing-section",
+      include_str!("../tests/fixtures/migrate/existing-section.v1.yaml"),
+      include_str!("../tests/fixtures/migrate/existing-section.v2.yaml"),
+    ),
+    (
+      "crlf",
+      include_str!("../tests/fixtures/migrate/crlf.v1.yaml"),
+      include_str!("../tests/fixtures/migrate/crlf.v2.yaml"),
+    ),
+  ];
+
+  #[test]
+  fn v1_to_v2() {
+    for (name, v1, v2) in FIXTURES {
+      let migrated = migrate(v1).unwrap().unwrap();
+      assert_eq!(migrated.from, 1, "{}", name);
+      assert!(migrated.preserves_text, "{}", name);
+      assert_eq!(migrated.contents, *v2, "{}", name);
+      // Upgrading twice does nothing.
+      assert!(migrate(v2).unwrap().is_none(), "{}", name);
+    }
+  }
+
+  #[test]
+  fn flow_sections_are_only_upgraded_in_memory() {
+    let migrated = migrate("tray: {}\nhide_tray_icon: true # hidden\n")
+      .unwrap()
+      .unwrap();
+    assert!(!migrated.preserves_text);
+    assert_eq!(migrated.contents, "version: 2\ntray:\n  hide_icon: true\n");
+  }
+
+  #[test]
+  fn nothing_to_upgrade() {
+    assert!(migrate("window_rules: []\n").unwrap().is_none());
+    assert!(migrate("version: 2\nwindow_rules: []\n").unwrap().is_none());
+    // Left for the parser to report.
+    assert!(migrate("window_rules: [\n").unwrap().is_none());
+  }
+
+  #[test]
+  fn unsupported_versions() {
+    assert!(matches!(
+      migrate("version: 3\n"),
+      Err(err) if err.starts_with("Unsupported config version 3")
+    ));
+    assert!(migrate("version: 0\n").is_err());
+    assert!(migrate("version: \"two\"\n").is_err());
+  }
+}
@@ -0,0 +1,270 @@
+// Adds rules to config.yaml from the tray. The file is edited as text so
+// comments and everything else stay the way the user wrote them.
+
+use std::fs;
+
+use crate::backend::WindowInfo;
+use crate::util::get_file_path;
+
+const PLACEHOLDER_ACTIVE_COLOR: &str = "#c6a0f6";
+const PLACEHOLDER_INACTIVE_COLOR: &str = "#ffffff";
+
+/// What a new rule matches on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleTarget {
+  Class(String),
+  /// Executable name, e.g. `firefox.exe`.
+  Process(String),
+}
+
+impl RuleTarget {
+  /// e.g. `class:MozillaWindowClass`, for menu ids.
+  pub fn to_id(&self) -> String {
+    match self {
+      RuleTarget::Class(class) => format!("class:{}", class),
+      RuleTarget::Process(process) => format!("process:{}", process),
+    }
+  }
+
+  pub fn from_id(id: &str) -> Option<Self> {
+    match id.split_once(':')? {
+      ("class", class) => Some(RuleTarget::Class(class.to_string())),
+      ("process", process) => Some(RuleTarget::Process(process.to_string())),
+      _ => None,
+    }
+  }
+
+  /// The rule as YAML, one list item without indentation.
+  fn rule_lines(&self) -> Vec<String> {
+    let (rule_match, value) = match self {
+      RuleTarget::Class(class) => ("Class", class),
+      RuleTarget::Process(process) => ("Process", process),
+    };
+    // JSON strings are valid double quoted YAML strings.
+    let value = serde_json::to_string(value).unwrap_or_default();
+    vec![
+      "# Added from the tray, change the colors to your liking".to_string(),
+      format!("- match: \"{}\"", rule_match),
+      format!("  contains: {}", value),
+      "  match_mode: \"exact\"".to_string(),
+      format!("  active_border_color: \"{}\"", PLACEHOLDER_ACTIVE_COLOR),
+      format!(
+        "  inactive_border_color: \"{}\"",
+        PLACEHOLDER_INACTIVE_COLOR
+      ),
+    ]
+  }
+}
+
+/// The rule a tray item creates: one matching `window`'s class, or without
+/// a window the "All ... windows" one matching the executable name
+/// `process`. `None` if there is nothing to match on, like an unknown process.
+pub fn rule_for(process: &str, window: Option<&WindowInfo>) -> Option<RuleTarget> {
+  match window {
+    None if process.is_empty() => None,
+    None => Some(RuleTarget::Process(process.to_string())),
+    Some(window) if window.class.is_empty() => None,
+    Some(window) => Some(RuleTarget::Class(window.class.clone())),
+  }
+}
+
+/// Appends a rule for `target` to config.yaml. The watcher picks it up
+/// like any other change.
+pub fn add_to_config(target: &RuleTarget) -> Result<(), String> {
+  let path = get_file_path("config.yaml");
+  let contents =
+    fs::read_to_string(&path).map_err(|err| format!("Failed to read config file: {}", err))?;
+  let contents = append_rule(&contents, target)?;
+  fs::write(&path, contents).map_err(|err| format!("Failed to write config file: {}", err))
+}
+
+/// Puts the rule after the last item of the top-level `window_rules` list,
+/// before any comments that follow it. Fails instead of guessing if the
+/// list isn't written as plain `- ` items.
+pub fn append_rule(contents: &str, target: &RuleTarget) -> Result<String, String> {
+  let newline = if contents.contains("\r\n") {
+    "\r\n"
+  } else {
+    "\n"
+  };
+  let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+
+  let key = lines
+    .iter()
+    .position(|line| line.starts_with("window_rules:"))
+    .ok_or("config.yaml has no `window_rules`")?;
+  let value = lines[key]["window_rules:".len()..]
+    .split(" #")
+    .next()
+    .unwrap_or_default()
+    .trim();
+
+  let (position, indent) = match value {
+    "" => list_end(&lines, key),
+    "[]" => {
+      let comment = lines[key]
+        .find(" #")
+        .map(|at| lines[key][at..].to_string())
+        .unwrap_or_default();
+      lines[key] = format!("window_rules:{}", comment);
+      (key, 2)
+    }
+    _ => return Err("`window_rules` isn't a list of `- ` items, add the rule by hand".to_string()),
+  };
+  let indent = " ".repeat(indent);
+  let rule = target
+    .rule_lines()
+    .into_iter()
+    .map(|line| format!("{}{}", indent, line));
+  lines.splice(position + 1..position + 1, rule);
+
+  let mut result = lines.join(newline);
+  if contents.ends_with('\n') || contents.is_empty() {
+    result.push_str(newline);
+  }
+
+  // Makes sure the edit did what it was supposed to before touching the file.
+  if rule_count(&result) != rule_count(contents).map(|count| count + 1) {
+    return Err("Couldn't add the rule to config.yaml, add it by hand".to_string());
+  }
+  Ok(result)
+}
+
+/// Last line of the list that starts at `key` and how far its items are
+/// indented. Comments and blank lines don't end the list, the next
+/// top-level key does.
+fn list_end(lines: &[String], key: usize) -> (usize, usize) {
+  let mut last = key;
+  let mut indent = None;
+  for (index, line) in lines.iter().enumerate().skip(key + 1) {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+      continue;
+    }
+    let line_indent = line.len() - trimmed.len();
+    if line_indent == 0 && (!trimmed.starts_with('-') || trimmed.starts_with("---")) {
+      break;
+    }
+    if indent.is_none() && trimmed.starts_with('-') {
+      indent = Some(line_indent);
+    }
+    last = index;
+  }
+  (last, indent.unwrap_or(2))
+}
+
+fn rule_count(contents: &str) -> Option<usize> {
+  let config: serde_yaml::Value = serde_yaml::from_str(contents).ok()?;
+  match config.get("window_rules")? {
+    serde_yaml::Value::Sequence(rules) => Some(rules.len()),
+    serde_yaml::Value::Null => Some(0),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::window;
+
+  const FIREFOX: &str = "MozillaWindowClass";
+
+  fn firefox() -> RuleTarget {
+    RuleTarget::Class(FIREFOX.to_string())
+  }
+
+  // Golden files, a Class rule for Firefox is added to `<name>.yaml` to get
+  // `<name>.out.yaml`.
+  const FIXTURES: &[(&str, &str, &str)] = &[
+    (
+      "default",
+      include_str!("data/config.yaml"),
+      include_str!("../tests/fixtures/new_rule/default.out.yaml"),
+    ),
+    (
+      "empty-list",
+      include_str!("../tests/fixtures/new_rule/empty-list.yaml"),
+      include_str!("../tests/fixtures/new_rule/empty-list.out.yaml"),
+    ),
+    (
+      "unindented",
+      include_str!("../tests/fixtures/new_rule/unindented.yaml"),
+      include_str!("../tests/fixtures/new_rule/unindented.out.yaml"),
+    ),
+    (
+      "crlf",
+      include_str!("../tests/fixtures/new_rule/crlf.yaml"),
+      include_str!("../tests/fixtures/new_rule/crlf.out.yaml"),
+    ),
+    (
+      "no-trailing-newline",
+      include_str!("../tests/fixtures/new_rule/no-trailing-newline.yaml"),
+      include_str!("../tests/fixtures/new_rule/no-trailing-newline.out.yaml"),
+    ),
+    (
+      "key-after",
+      include_str!("../tests/fixtures/new_rule/key-after.yaml"),
+      include_str!("../tests/fixtures/new_rule/key-after.out.yaml"),
+    ),
+  ];
+
+  #[test]
+  fn rule_is_added_at_the_end_of_the_list() {
+    for (name, before, after) in FIXTURES {
+      assert_eq!(
+        append_rule(before, &firefox()).as_deref(),
+        Ok(*after),
+        "{}",
+        name
+      );
+    }
+  }
+
+  #[test]
+  fn configs_it_cant_edit() {
+    assert_eq!(
+      append_rule("theme: \"nord\"\n", &firefox()),
+      Err("config.yaml has no `window_rules`".to_string())
+    );
+    assert_eq!(
+      append_rule("window_rules: [{ match: \"Global\" }]\n", &firefox()),
+      Err("`window_rules` isn't a list of `- ` items, add the rule by hand".to_string())
+    );
+  }
+
+  #[test]
+  fn process_values_are_quoted() {
+    let rule = RuleTarget::Process("my \"app\".exe".to_string());
+    let contents = append_rule("window_rules: []\n", &rule).unwrap();
+    assert!(
+      contents.contains("    contains: \"my \\\"app\\\".exe\"\n"),
+      "{}",
+      contents
+    );
+  }
+
+  #[test]
+  fn menu_items_pick_a_process_or_class_rule() {
+    let firefox_window = window(1, 10, "Mozilla Firefox", FIREFOX);
+    assert_eq!(
+      rule_for("firefox.exe", None),
+      Some(RuleTarget::Process("firefox.exe".to_string()))
+    );
+    assert_eq!(
+      rule_for("firefox.exe", Some(&firefox_window)),
+      Some(firefox())
+    );
+    // Windows of unknown processes can still get a class rule.
+    assert_eq!(rule_for("", None), None);
+    assert_eq!(rule_for("", Some(&firefox_window)), Some(firefox()));
+    assert_eq!(rule_for("app.exe", Some(&window(2, 10, "", ""))), None);
+  }
+
+  #[test]
+  fn ids_round_trip() {
+    for target in [firefox(), RuleTarget::Process("a:b.exe".to_string())] {
+      assert_eq!(RuleTarget::from_id(&target.to_id()), Some(target));
+    }
+    assert_eq!(RuleTarget::from_id("title:Firefox"), None);
+  }
+}
@@ -0,0 +1,211 @@
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+#[cfg(any(not(windows), test))]
+use crate::logger::Logger;
+
+lazy_static! {
+  static ref NOTIFIER: Mutex<Notifier> = Mutex::new(Notifier::default());
+}
+
+/// Keeps errors from piling up while the config is being edited, e.g. an
+/// editor that saves on every keystroke. Only one message is open at a
+/// time and an error that was already shown isn't shown again until the
+/// config loads fine.
+#[derive(Debug, Default)]
+struct Notifier {
+  showing: bool,
+  /// The last error shown, or the one that is still open.
+  shown: Option<String>,
+  /// The newest error that came in while a message was open.
+  pending: Option<String>,
+}
+
+impl Notifier {
+  /// The message to show now, if any.
+  fn push(&mut self, message: &str) -> Option<String> {
+    if self.shown.as_deref() == Some(message) {
+      // The open message already says the same thing.
+      self.pending = None;
+      return None;
+    }
+    if self.showing {
+      self.pending = Some(message.to_string());
+      return None;
+    }
+    self.showing = true;
+    self.shown = Some(message.to_string());
+    self.shown.clone()
+  }
+
+  /// Called once the open message is closed, returns the next one to show.
+  fn closed(&mut self) -> Option<String> {
+    self.showing = false;
+    let pending = self.pending.take()?;
+    self.push(&pending)
+  }
+
+  fn clear(&mut self) {
+    self.shown = None;
+    self.pending = None;
+  }
+}
+
+/// Shows an error to the user without blocking the caller.
+pub fn show_error(message: &str) {
+  let Some(message) = NOTIFIER.lock().unwrap().push(message) else {
+    return;
+  };
+  std::thread::spawn(move || {
+    let mut message = Some(message);
+    while let Some(text) = message {
+      show_message(&text);
+      message = NOTIFIER.lock().unwrap().closed();
+    }
+  });
+}
+
+/// Whatever went wrong before is fixed, the same error is worth showing again.
+pub fn clear() {
+  NOTIFIER.lock().unwrap().clear();
+}
+
+/// Shows `message` without taking focus from whatever is being typed in.
+/// Only blocks for the message box fallback, until it is closed.
+#[cfg(all(windows, not(test)))]
+fn show_message(message: &str) {
+  if !balloon::show(message) {
+    balloon::message_box(message);
+  }
+}
+
+/// No tray off-Windows (or in tests), the log is the closest thing.
+#[cfg(any(not(windows), test))]
+fn show_message(message: &str) {
+  Logger::log(&format!("[INFO] {}", message));
+}
+
+#[cfg(all(windows, not(test)))]
+mod balloon {
+  use std::ffi::OsStr;
+  use std::os::windows::ffi::OsStrExt;
+  use winapi::shared::windef::HWND;
+  use winapi::um::processthreadsapi::GetCurrentProcessId;
+  use winapi::um::shellapi::{
+    Shell_NotifyIconW, NIF_INFO, NIIF_WARNING, NIM_MODIFY, NOTIFYICONDATAW,
+  };
+  use winapi::um::winuser::{
+    FindWindowExW, GetWindowThreadProcessId, MessageBoxW, MB_ICONWARNING, MB_OK,
+  };
+
+  // tray-icon puts its icon on a hidden "tray_icon_app" window, numbering
+  // icons from 1. cute-borders only ever creates the one.
+  const TRAY_WINDOW_CLASS: &str = "tray_icon_app";
+  const TRAY_ICON_ID: u32 = 1;
+
+  fn wide(text: &str) -> Vec<u16> {
+    OsStr::new(text)
+      .encode_wide()
+      .chain(std::iter::once(0))
+      .collect()
+  }
+
+  /// `text` cut to fit `buffer`, which stays null terminated.
+  fn copy(buffer: &mut [u16], text: &str) {
+    let text: Vec<u16> = OsStr::new(text).encode_wide().collect();
+    let len = text.len().min(buffer.len() - 1);
+    buffer[..len].copy_from_slice(&text[..len]);
+  }
+
+  fn tray_window() -> Option<HWND> {
+    let class = wide(TRAY_WINDOW_CLASS);
+    let mut hwnd = std::ptr::null_mut();
+    loop {
+      hwnd = unsafe { FindWindowExW(std::ptr::null_mut(), hwnd, class.as_ptr(), std::ptr::null()) };
+      if hwnd.is_null() {
+        return None;
+      }
+      let mut pid = 0;
+      unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
+      if pid == unsafe { GetCurrentProcessId() } {
+        return Some(hwnd);
+      }
+    }
+  }
+
+  /// A balloon on the tray icon, `false` if there is no icon to show it on
+  /// (e.g. `tray.hide_icon`).
+  pub fn show(message: &str) -> bool {
+    let Some(hwnd) = tray_window() else {
+      return false;
+    };
+    let mut data: NOTIFYICONDATAW = unsafe { std::mem::zeroed() };
+    data.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
+    data.hWnd = hwnd;
+    data.uID = TRAY_ICON_ID;
+    data.uFlags = NIF_INFO;
+    data.dwInfoFlags = NIIF_WARNING;
+    copy(&mut data.szInfoTitle, "cute-borders");
+    copy(&mut data.szInfo, message);
+    unsafe { Shell_NotifyIconW(NIM_MODIFY, &mut data) != 0 }
+  }
+
+  // Not brought to the foreground, so it doesn't interrupt typing either.
+  pub fn message_box(message: &str) {
+    let text = wide(message);
+    let caption = wide("cute-borders");
+    unsafe {
+      MessageBoxW(
+        std::ptr::null_mut(),
+        text.as_ptr(),
+        caption.as_ptr(),
+        MB_OK | MB_ICONWARNING,
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn one_message_at_a_time() {
+    let mut notifier = Notifier::default();
+    assert_eq!(notifier.push("a").as_deref(), Some("a"));
+    assert_eq!(notifier.push("b"), None);
+    assert_eq!(notifier.push("c"), None);
+    // Only the newest one is left once the first is closed.
+    assert_eq!(notifier.closed().as_deref(), Some("c"));
+    assert_eq!(notifier.closed(), None);
+  }
+
+  #[test]
+  fn same_error_is_only_shown_once() {
+    let mut notifier = Notifier::default();
+    assert_eq!(notifier.push("a").as_deref(), Some("a"));
+    assert_eq!(notifier.push("a"), None);
+    assert_eq!(notifier.closed(), None);
+    assert_eq!(notifier.push("a"), None);
+
+    // Back to the error that is already open.
+    assert_eq!(notifier.push("b").as_deref(), Some("b"));
+    assert_eq!(notifier.push("c"), None);
+    assert_eq!(notifier.push("b"), None);
+    assert_eq!(notifier.closed(), None);
+  }
+
+  #[test]
+  fn fixed_errors_are_shown_again() {
+    let mut notifier = Notifier::default();
+    assert_eq!(notifier.push("a").as_deref(), Some("a"));
+    assert_eq!(notifier.closed(), None);
+    notifier.clear();
+    assert_eq!(notifier.push("a").as_deref(), Some("a"));
+
+    // Fixed while the message was still open, nothing left to show.
+    assert_eq!(notifier.push("b"), None);
+    notifier.clear();
+    assert_eq!(notifier.closed(), None);
+  }
+}
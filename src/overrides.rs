@@ -0,0 +1,58 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::backend::Hwnd;
+use crate::color::Color;
+
+lazy_static! {
+  static ref OVERRIDES: Mutex<Overrides> = Mutex::new(Overrides::default());
+}
+
+/// Border colors set on single windows over IPC. They win over every rule
+/// and are forgotten once the window is gone.
+#[derive(Default)]
+pub struct Overrides {
+  colors: HashMap<Hwnd, Color>,
+}
+
+impl Overrides {
+  /// `None` removes the override, the rules decide again.
+  pub fn set(hwnd: Hwnd, color: Option<Color>) {
+    let mut overrides = OVERRIDES.lock().unwrap();
+    match color {
+      Some(color) => overrides.colors.insert(hwnd, color),
+      None => overrides.colors.remove(&hwnd),
+    };
+  }
+
+  pub fn get(hwnd: Hwnd) -> Option<Color> {
+    OVERRIDES.lock().unwrap().colors.get(&hwnd).copied()
+  }
+
+  /// Whether any override changes over time, like `Config::is_animated`.
+  pub fn is_animated() -> bool {
+    let overrides = OVERRIDES.lock().unwrap();
+    overrides
+      .colors
+      .values()
+      .any(|color| *color == Color::Rainbow)
+  }
+
+  pub fn count() -> usize {
+    OVERRIDES.lock().unwrap().colors.len()
+  }
+
+  pub fn remove(hwnd: Hwnd) {
+    OVERRIDES.lock().unwrap().colors.remove(&hwnd);
+  }
+
+  pub fn hwnds() -> Vec<Hwnd> {
+    OVERRIDES.lock().unwrap().colors.keys().copied().collect()
+  }
+
+  #[cfg(test)]
+  pub fn clear() {
+    OVERRIDES.lock().unwrap().colors.clear();
+  }
+}
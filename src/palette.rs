@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::color::{Color, ColorParseError};
+
+/// Palettes for `theme:`, in the same format as `colors:`.
+const THEMES: &[(&str, &str)] = &[
+  (
+    "catppuccin-latte",
+    include_str!("data/themes/catppuccin-latte.yaml"),
+  ),
+  (
+    "catppuccin-frappe",
+    include_str!("data/themes/catppuccin-frappe.yaml"),
+  ),
+  (
+    "catppuccin-macchiato",
+    include_str!("data/themes/catppuccin-macchiato.yaml"),
+  ),
+  (
+    "catppuccin-mocha",
+    include_str!("data/themes/catppuccin-mocha.yaml"),
+  ),
+  ("nord", include_str!("data/themes/nord.yaml")),
+  ("dracula", include_str!("data/themes/dracula.yaml")),
+  ("gruvbox", include_str!("data/themes/gruvbox.yaml")),
+  ("tokyo-night", include_str!("data/themes/tokyo-night.yaml")),
+];
+
+pub fn theme_names() -> impl Iterator<Item = &'static str> {
+  THEMES.iter().map(|(name, _)| *name)
+}
+
+/// The colors of a bundled theme, e.g. `catppuccin-mocha`.
+pub fn theme(name: &str) -> Result<BTreeMap<String, String>, String> {
+  let Some((_, theme)) = THEMES.iter().find(|(theme, _)| *theme == name) else {
+    let names: Vec<&str> = theme_names().collect();
+    return Err(format!(
+      "Unknown theme `{}`, expected one of: {}",
+      name,
+      names.join(", ")
+    ));
+  };
+  serde_yaml::from_str(theme).map_err(|err| format!("Failed to parse theme `{}`: {}", name, err))
+}
+
+/// Named colors rules can use as `$name`, resolved once when the config loads.
+#[derive(Debug, Default, Clone)]
+pub struct Palette {
+  colors: HashMap<String, Color>,
+}
+
+impl Palette {
+  /// Resolves every variable, including ones that reference other variables.
+  /// Broken variables are returned as errors and resolve to `error_color`
+  /// so rules using them don't report the same problem again.
+  pub fn new(
+    variables: &BTreeMap<String, String>,
+    error_color: Color,
+  ) -> (Self, Vec<ColorParseError>) {
+    let mut resolver = Resolver {
+      variables,
+      resolved: HashMap::new(),
+      stack: Vec::new(),
+      errors: Vec::new(),
+    };
+    let colors = variables
+      .keys()
+      .map(|name| {
+        let color = resolver.resolve(name).unwrap_or(error_color);
+        (name.clone(), color)
+      })
+      .collect();
+
+    (Palette { colors }, resolver.errors)
+  }
+
+  /// Like `Color::from_str`, but also looks up `$name`.
+  pub fn parse(&self, value: &str) -> Result<Color, ColorParseError> {
+    match variable_name(value) {
+      Some(name) => self
+        .colors
+        .get(name)
+        .copied()
+        .ok_or_else(|| ColorParseError::unknown_variable(value)),
+      None => value.parse(),
+    }
+  }
+}
+
+fn variable_name(value: &str) -> Option<&str> {
+  value.trim().strip_prefix('$')
+}
+
+struct Resolver<'a> {
+  variables: &'a BTreeMap<String, String>,
+  /// `None` for variables that are broken.
+  resolved: HashMap<&'a str, Option<Color>>,
+  /// Variables currently being resolved, to find cycles.
+  stack: Vec<&'a str>,
+  errors: Vec<ColorParseError>,
+}
+
+impl<'a> Resolver<'a> {
+  // Every broken variable is only reported once, by the first one that
+  // runs into it. Variables depending on it silently become broken too.
+  fn resolve(&mut self, name: &'a str) -> Option<Color> {
+    if let Some(color) = self.resolved.get(name) {
+      return *color;
+    }
+    let location = format!("colors.{}", name);
+    if let Some(start) = self.stack.iter().position(|other| *other == name) {
+      let path = self.stack[start..]
+        .iter()
+        .chain([&name])
+        .map(|name| format!("${}", name))
+        .collect();
+      let value = &self.variables[name];
+      self
+        .errors
+        .push(ColorParseError::cycle(value, path).at(&location));
+      return None;
+    }
+
+    let value = &self.variables[name];
+    self.stack.push(name);
+    let color = match variable_name(value) {
+      Some(reference) => match self.variables.get_key_value(reference) {
+        Some((reference, _)) => self.resolve(reference),
+        None => {
+          self
+            .errors
+            .push(ColorParseError::unknown_variable(value).at(&location));
+          None
+        }
+      },
+      None => match value.parse::<Color>() {
+        Ok(color) => Some(color),
+        Err(err) => {
+          self.errors.push(err.at(&location));
+          None
+        }
+      },
+    };
+    self.stack.pop();
+
+    self.resolved.insert(name, color);
+    color
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing;
+
+  #[test]
+  fn every_theme_color_parses() {
+    for name in theme_names() {
+      let colors = theme(name).unwrap();
+      assert!(!colors.is_empty(), "`{}` has no colors", name);
+      let (palette, errors) = Palette::new(&colors, Color::Default);
+      assert!(errors.is_empty(), "`{}`: {:?}", name, errors);
+      for key in colors.keys() {
+        let color = palette.parse(&format!("${}", key)).unwrap();
+        assert!(
+          matches!(color, Color::Rgb(..)),
+          "`{}` in `{}` is {:?}",
+          key,
+          name,
+          color
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn catppuccin_flavours_have_the_same_colors() {
+    let mocha = theme("catppuccin-mocha").unwrap();
+    for flavour in [
+      "catppuccin-latte",
+      "catppuccin-frappe",
+      "catppuccin-macchiato",
+    ] {
+      let colors = theme(flavour).unwrap();
+      assert!(colors.keys().eq(mocha.keys()), "{}", flavour);
+    }
+    assert_eq!(mocha["mauve"], "#cba6f7");
+  }
+
+  #[test]
+  fn unknown_theme() {
+    let err = theme("catppuccin").unwrap_err();
+    assert!(err.starts_with("Unknown theme `catppuccin`, expected one of: catppuccin-latte"));
+  }
+
+  const RED: Color = Color::Rgb(255, 0, 0);
+
+  /// A config with `colors` and a Global rule using `border`.
+  fn config(colors: &str, border: &str) -> crate::config::Config {
+    testing::config(&format!(
+      "colors:\n{}\nwindow_rules:\n  - match: \"Global\"\n    active_border_color: \"{}\"\n",
+      colors, border
+    ))
+  }
+
+  fn border(config: &crate::config::Config) -> Option<Color> {
+    config.window_rules[0].style(true).border_color
+  }
+
+  #[test]
+  fn cycles_are_reported_once() {
+    let config = config("  a: \"$b\"\n  b: \"$a\"", "$a");
+    assert_eq!(
+      config.errors,
+      vec!["Color variables reference each other: $a -> $b -> $a in colors.a"]
+    );
+    // Rules using it get `error_color` without another error.
+    assert_eq!(border(&config), Some(RED));
+  }
+
+  #[test]
+  fn variables_cant_reference_themselves() {
+    let config = config("  a: \"$a\"", "$a");
+    assert_eq!(
+      config.errors,
+      vec!["Color variables reference each other: $a -> $a in colors.a"]
+    );
+    assert_eq!(border(&config), Some(RED));
+  }
+
+  #[test]
+  fn variables_can_reference_each_other() {
+    let config = config("  a: \"$b\"\n  b: \"#fff\"", "$a");
+    assert!(config.errors.is_empty(), "{:?}", config.errors);
+    assert_eq!(border(&config), Some(Color::Rgb(255, 255, 255)));
+  }
+
+  #[test]
+  fn unknown_variables_in_rules() {
+    let config = config("  a: \"#fff\"", "$nope");
+    assert_eq!(
+      config.errors,
+      vec!["Unknown color variable `$nope` in window_rules[0].active_border_color"]
+    );
+    assert_eq!(border(&config), Some(RED));
+  }
+}
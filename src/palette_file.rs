@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+
+/// Reads the colors out of a pywal `colors.json`, a wallust `colors.json`
+/// or a base16 YAML scheme, e.g. `color0`..`color15` or `base00`..`base0F`.
+pub fn load(path: &str) -> Result<BTreeMap<String, String>, String> {
+  let contents =
+    fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+  let colors = if path.to_ascii_lowercase().ends_with(".json") {
+    parse_json(&contents)
+  } else {
+    parse_yaml(&contents)
+  };
+  colors.map_err(|err| format!("Failed to parse {}: {}", path, err))
+}
+
+/// pywal nests its colors under `colors` and `special`, wallust can also
+/// write them all at the top level.
+pub fn parse_json(contents: &str) -> Result<BTreeMap<String, String>, String> {
+  let json: JsonValue = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+  let mut colors = BTreeMap::new();
+  for object in [Some(&json), json.get("special"), json.get("colors")]
+    .into_iter()
+    .flatten()
+  {
+    let Some(object) = object.as_object() else {
+      continue;
+    };
+    for (key, value) in object {
+      if let Some(value) = value.as_str() {
+        insert(&mut colors, key, value);
+      }
+    }
+  }
+  finish(colors)
+}
+
+/// base16 schemes either have `base00: "181818"` at the top level, or
+/// (in the newer tinted-theming format) under `palette`.
+pub fn parse_yaml(contents: &str) -> Result<BTreeMap<String, String>, String> {
+  let yaml: YamlValue = serde_yaml::from_str(contents).map_err(|err| err.to_string())?;
+  let mut colors = BTreeMap::new();
+  for mapping in [Some(&yaml), yaml.get("palette")].into_iter().flatten() {
+    let Some(mapping) = mapping.as_mapping() else {
+      continue;
+    };
+    for (key, value) in mapping {
+      if let (Some(key), Some(value)) = (key.as_str(), value.as_str()) {
+        insert(&mut colors, key, value);
+      }
+    }
+  }
+  finish(colors)
+}
+
+// Everything else in these files (wallpaper, scheme name, author...)
+// is skipped. base16 leaves out the `#`.
+fn insert(colors: &mut BTreeMap<String, String>, key: &str, value: &str) {
+  if !is_color_key(key) {
+    return;
+  }
+  let value = if value.len() == 6 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+    format!("#{}", value)
+  } else {
+    value.to_string()
+  };
+  colors.insert(key.to_string(), value);
+}
+
+fn is_color_key(key: &str) -> bool {
+  if let Some(index) = key.strip_prefix("color") {
+    return !index.is_empty() && index.chars().all(|c| c.is_ascii_digit());
+  }
+  if let Some(index) = key.strip_prefix("base") {
+    return index.len() == 2 && index.chars().all(|c| c.is_ascii_hexdigit());
+  }
+  matches!(key, "background" | "foreground" | "cursor")
+}
+
+fn finish(colors: BTreeMap<String, String>) -> Result<BTreeMap<String, String>, String> {
+  if colors.is_empty() {
+    return Err("No colors found, expected color0..color15 or base00..base0F".to_string());
+  }
+  Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::color::Color;
+  use crate::palette::Palette;
+
+  fn fixture(name: &str) -> String {
+    format!(
+      "{}/tests/fixtures/palette_file/{}",
+      env!("CARGO_MANIFEST_DIR"),
+      name
+    )
+  }
+
+  // Every loaded value has to be usable as a color.
+  fn load_fixture(name: &str) -> BTreeMap<String, String> {
+    let colors = load(&fixture(name)).unwrap();
+    let (_, errors) = Palette::new(&colors, Color::Default);
+    assert!(errors.is_empty(), "{}: {:?}", name, errors);
+    colors
+  }
+
+  #[test]
+  fn pywal() {
+    let colors = load_fixture("pywal.json");
+    // color0..color15 plus background, foreground and cursor.
+    assert_eq!(colors.len(), 19);
+    assert_eq!(colors["color1"], "#f7768e");
+    assert_eq!(colors["color15"], "#c0caf5");
+    assert_eq!(colors["background"], "#1a1b26");
+    assert!(!colors.contains_key("wallpaper"));
+    assert!(!colors.contains_key("alpha"));
+  }
+
+  #[test]
+  fn wallust() {
+    assert_eq!(load_fixture("wallust.json"), load_fixture("pywal.json"));
+  }
+
+  #[test]
+  fn base16() {
+    let colors = load_fixture("base16.yaml");
+    assert_eq!(colors.len(), 16);
+    assert_eq!(colors["base00"], "#1d1f21");
+    assert_eq!(colors["base0F"], "#a3685a");
+    assert!(!colors.contains_key("scheme"));
+  }
+
+  #[test]
+  fn tinted_theming() {
+    assert_eq!(
+      load_fixture("tinted-theming.yaml"),
+      load_fixture("base16.yaml")
+    );
+  }
+
+  #[test]
+  fn files_without_colors() {
+    let err = load(&fixture("no-colors.json")).unwrap_err();
+    assert!(err.ends_with("No colors found, expected color0..color15 or base00..base0F"));
+    assert!(parse_yaml("scheme: \"Empty\"").is_err());
+    assert!(parse_json("[\"#ffffff\"]").is_err());
+  }
+
+  #[test]
+  fn broken_files() {
+    assert!(parse_json("{ \"color0\": ").is_err());
+    let err = load(&fixture("missing.json")).unwrap_err();
+    assert!(err.starts_with("Failed to read"), "{}", err);
+  }
+
+  #[test]
+  fn color_keys() {
+    for key in [
+      "color0",
+      "color15",
+      "base00",
+      "base0F",
+      "background",
+      "cursor",
+    ] {
+      assert!(is_color_key(key), "{}", key);
+    }
+    for key in [
+      "color",
+      "colorful",
+      "base0",
+      "base0G",
+      "base000",
+      "wallpaper",
+    ] {
+      assert!(!is_color_key(key), "{}", key);
+    }
+  }
+}
@@ -0,0 +1,48 @@
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+  static ref PAUSE: Mutex<Pause> = Mutex::new(Pause::default());
+}
+
+/// While paused every window keeps its default colors and events are
+/// ignored, see `Scheduler::handle`. Timed pauses run out by themselves,
+/// the scheduler wakes up for that.
+#[derive(Default)]
+pub struct Pause {
+  paused: bool,
+  /// `None` pauses until `resume`.
+  until: Option<Instant>,
+}
+
+impl Pause {
+  /// Pauses until `resume`, or for `duration`. Pausing again replaces
+  /// the previous duration.
+  pub fn pause(duration: Option<Duration>) {
+    let mut pause = PAUSE.lock().unwrap();
+    pause.paused = true;
+    // Too far in the future to represent is as good as forever.
+    pause.until = duration.and_then(|duration| Instant::now().checked_add(duration));
+  }
+
+  pub fn resume() {
+    let mut pause = PAUSE.lock().unwrap();
+    pause.paused = false;
+    pause.until = None;
+  }
+
+  pub fn is_paused() -> bool {
+    let pause = PAUSE.lock().unwrap();
+    pause.paused && pause.until.is_none_or(|until| Instant::now() < until)
+  }
+
+  /// Time left of a timed pause, `None` if not paused or paused until resumed.
+  pub fn remaining() -> Option<Duration> {
+    if !Self::is_paused() {
+      return None;
+    }
+    let until = PAUSE.lock().unwrap().until?;
+    Some(until.saturating_duration_since(Instant::now()))
+  }
+}
@@ -0,0 +1,105 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::backend::{Hwnd, WindowBackend, WindowInfo};
+
+lazy_static! {
+  static ref PROCESS_CACHE: Mutex<ProcessCache> = Mutex::new(ProcessCache::default());
+}
+
+/// Image paths by PID, so every process is only looked up once.
+/// Failed lookups are cached as well, they won't succeed on a retry.
+#[derive(Default)]
+pub struct ProcessCache {
+  paths: HashMap<u32, Option<String>>,
+  /// Which windows a PID was looked up for, it is forgotten with the last
+  /// one of them since PIDs get reused.
+  windows: HashMap<Hwnd, u32>,
+}
+
+impl ProcessCache {
+  pub fn path(backend: &dyn WindowBackend, window: &WindowInfo) -> Option<String> {
+    let mut cache = PROCESS_CACHE.lock().unwrap();
+    cache.windows.insert(window.hwnd, window.pid);
+    cache
+      .paths
+      .entry(window.pid)
+      .or_insert_with(|| backend.process_path(window.pid))
+      .clone()
+  }
+
+  /// Called once `hwnd` is destroyed. Its process is forgotten unless
+  /// another window still belongs to it.
+  pub fn forget(hwnd: Hwnd) {
+    let mut cache = PROCESS_CACHE.lock().unwrap();
+    let Some(pid) = cache.windows.remove(&hwnd) else {
+      return;
+    };
+    if !cache.windows.values().any(|other| *other == pid) {
+      cache.paths.remove(&pid);
+    }
+  }
+
+  /// Every window a process was looked up for.
+  pub fn hwnds() -> Vec<Hwnd> {
+    PROCESS_CACHE
+      .lock()
+      .unwrap()
+      .windows
+      .keys()
+      .copied()
+      .collect()
+  }
+
+  #[cfg(test)]
+  pub fn clear() {
+    let mut cache = PROCESS_CACHE.lock().unwrap();
+    cache.paths.clear();
+    cache.windows.clear();
+  }
+}
+
+/// `C:\Program Files\Mozilla Firefox\firefox.exe` -> `firefox.exe`
+pub fn file_name(path: &str) -> &str {
+  path.rsplit(['\\', '/']).next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::FakeBackend;
+  use crate::testing::{self, window};
+
+  #[test]
+  fn forgets_a_process_with_its_last_window() {
+    let _lock = testing::lock();
+    let backend = FakeBackend::default();
+    backend.set_process_path(10, r"C:\Windows\notepad.exe");
+    let first = window(1, 10, "Untitled", "Notepad");
+    let second = window(2, 10, "notes.txt", "Notepad");
+    ProcessCache::path(&backend, &first);
+    ProcessCache::path(&backend, &second);
+
+    // PID 10 is reused by another program once notepad is gone.
+    backend.set_process_path(10, r"C:\Program Files\Mozilla Firefox\firefox.exe");
+    ProcessCache::forget(1);
+    assert_eq!(
+      ProcessCache::path(&backend, &second).as_deref(),
+      Some(r"C:\Windows\notepad.exe")
+    );
+    ProcessCache::forget(2);
+    let firefox = window(3, 10, "Mozilla Firefox", "MozillaWindowClass");
+    assert_eq!(
+      ProcessCache::path(&backend, &firefox).as_deref(),
+      Some(r"C:\Program Files\Mozilla Firefox\firefox.exe")
+    );
+  }
+
+  #[test]
+  fn file_name_strips_the_directory() {
+    assert_eq!(file_name(r"C:\Windows\notepad.exe"), "notepad.exe");
+    assert_eq!(file_name("/usr/bin/firefox"), "firefox");
+    assert_eq!(file_name("code.exe"), "code.exe");
+  }
+}
@@ -0,0 +1,665 @@
+use serde::Serialize;
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::fmt;
+
+use crate::backend::{WindowBackend, WindowInfo};
+use crate::config::{RuleCondition, RuleMatch, Style, WindowRule};
+use crate::matcher::{MatchMode, Matcher};
+use crate::process::{file_name, ProcessCache};
+
+/// Everything a condition can look at. Kept behind a trait so rules can be
+/// evaluated against plain values as well as live windows.
+pub trait WindowFacts {
+  fn title(&self) -> &str;
+  fn class(&self) -> &str;
+  /// Full executable path, `None` if it couldn't be looked up.
+  fn process_path(&self) -> Option<&str>;
+}
+
+/// Plain values to evaluate rules against, e.g. from `explain --title`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowQuery {
+  pub title: String,
+  pub class: String,
+  /// Executable name or full path.
+  pub process_path: Option<String>,
+}
+
+impl WindowFacts for WindowQuery {
+  fn title(&self) -> &str {
+    &self.title
+  }
+
+  fn class(&self) -> &str {
+    &self.class
+  }
+
+  fn process_path(&self) -> Option<&str> {
+    self.process_path.as_deref()
+  }
+}
+
+/// A window from the backend, its process is only looked up once a
+/// condition actually asks for it.
+pub struct LiveWindow<'a> {
+  backend: &'a dyn WindowBackend,
+  window: &'a WindowInfo,
+  process_path: OnceCell<Option<String>>,
+}
+
+impl<'a> LiveWindow<'a> {
+  pub fn new(backend: &'a dyn WindowBackend, window: &'a WindowInfo) -> Self {
+    LiveWindow {
+      backend,
+      window,
+      process_path: OnceCell::new(),
+    }
+  }
+}
+
+impl WindowFacts for LiveWindow<'_> {
+  fn title(&self) -> &str {
+    &self.window.title
+  }
+
+  fn class(&self) -> &str {
+    &self.window.class
+  }
+
+  fn process_path(&self) -> Option<&str> {
+    self
+      .process_path
+      .get_or_init(|| ProcessCache::path(self.backend, self.window))
+      .as_deref()
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum Condition {
+  All(Vec<Condition>),
+  Any(Vec<Condition>),
+  Not(Box<Condition>),
+  Title(Matcher),
+  Class(Matcher),
+  /// Executable file name.
+  Process(Matcher),
+  /// Full executable path.
+  Path(Matcher),
+}
+
+impl Condition {
+  /// Every key set on a condition has to hold, so `{ class: a, process: b }`
+  /// is the same as `all: [{ class: a }, { process: b }]`.
+  pub fn compile(condition: &RuleCondition) -> Result<Self, String> {
+    let mode = condition.match_mode.unwrap_or_default();
+    let mut conditions = Vec::new();
+
+    if let Some(all) = &condition.all {
+      let all = all
+        .iter()
+        .map(Condition::compile)
+        .collect::<Result<_, _>>()?;
+      conditions.push(Condition::All(all));
+    }
+    if let Some(any) = &condition.any {
+      let any = any
+        .iter()
+        .map(Condition::compile)
+        .collect::<Result<_, _>>()?;
+      conditions.push(Condition::Any(any));
+    }
+    if let Some(not) = &condition.not {
+      conditions.push(Condition::Not(Box::new(Condition::compile(not)?)));
+    }
+    if let Some(title) = &condition.title {
+      conditions.push(Condition::Title(compile_pattern(title, mode)?));
+    }
+    if let Some(class) = &condition.class {
+      conditions.push(Condition::Class(compile_pattern(class, mode)?));
+    }
+    if let Some(process) = &condition.process {
+      conditions.push(Condition::Process(compile_pattern(process, mode)?));
+    }
+    if let Some(path) = &condition.path {
+      conditions.push(Condition::Path(compile_pattern(path, mode)?));
+    }
+
+    match conditions.len() {
+      0 => Err("Empty condition".to_string()),
+      1 => Ok(conditions.remove(0)),
+      _ => Ok(Condition::All(conditions)),
+    }
+  }
+
+  pub fn evaluate(&self, window: &dyn WindowFacts) -> bool {
+    match self {
+      Condition::All(conditions) => conditions.iter().all(|c| c.evaluate(window)),
+      Condition::Any(conditions) => conditions.iter().any(|c| c.evaluate(window)),
+      Condition::Not(condition) => !condition.evaluate(window),
+      Condition::Title(matcher) => matcher.is_match(window.title()),
+      Condition::Class(matcher) => matcher.is_match(window.class()),
+      Condition::Process(matcher) => window
+        .process_path()
+        .is_some_and(|path| matcher.is_match(file_name(path))),
+      Condition::Path(matcher) => window
+        .process_path()
+        .is_some_and(|path| matcher.is_match(path)),
+    }
+  }
+}
+
+fn compile_pattern(pattern: &str, mode: MatchMode) -> Result<Matcher, String> {
+  Matcher::new(pattern, mode).map_err(|err| format!("Invalid pattern `{}`: {}", pattern, err))
+}
+
+/// Turns either `match` + `contains` or `when` into a condition.
+/// Global rules don't have one.
+pub fn compile_rule(rule: &WindowRule) -> Result<Option<Condition>, String> {
+  let rule_match = match (&rule.rule_match, &rule.when) {
+    (Some(_), Some(_)) => return Err("`match` and `when` can't be used together".to_string()),
+    (None, None) => return Err("Expected either `match` or `when`".to_string()),
+    (None, Some(when)) => return Condition::compile(when).map(Some),
+    (Some(RuleMatch::Global), None) => return Ok(None),
+    (Some(rule_match), None) => rule_match,
+  };
+
+  let Some(pattern) = &rule.contains else {
+    return Err(format!("Expected `contains` on `match: {:?}`", rule_match));
+  };
+  let matcher = compile_pattern(pattern, rule.match_mode.unwrap_or_default())?;
+  let condition = match rule_match {
+    RuleMatch::Title => Condition::Title(matcher),
+    RuleMatch::Class => Condition::Class(matcher),
+    RuleMatch::Process => Condition::Process(matcher),
+    RuleMatch::Path => Condition::Path(matcher),
+    RuleMatch::Global => return Ok(None),
+  };
+  Ok(Some(condition))
+}
+
+/// What the matching rules ended up setting. Whatever is still `None`
+/// is left to the system default.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Resolved {
+  pub active: Style,
+  pub inactive: Style,
+}
+
+impl Resolved {
+  /// Takes everything from `rule` that hasn't been set by a rule before it.
+  fn inherit(&mut self, rule: &WindowRule) {
+    self.active.inherit(rule.style(true));
+    self.inactive.inherit(rule.style(false));
+  }
+}
+
+/// The order rules are looked at: highest `priority` first, then the active
+/// profile's rules before the main ones, then specific rules before Global
+/// ones, then the order they are written in.
+pub fn precedence_order(rules: &[WindowRule]) -> Vec<&WindowRule> {
+  let mut ordered: Vec<&WindowRule> = rules.iter().collect();
+  ordered.sort_by_key(|rule| {
+    (
+      Reverse(rule.priority.unwrap_or(0)),
+      !rule.from_profile,
+      rule.is_global(),
+    )
+  });
+  ordered
+}
+
+/// What happened to a rule while resolving a window.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+  Matched,
+  NotMatched,
+  /// A specific rule before it already matched.
+  Skipped,
+  /// Its condition failed to compile, so it never matches.
+  Invalid,
+}
+
+impl fmt::Display for Outcome {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Outcome::Matched => write!(f, "matched"),
+      Outcome::NotMatched => write!(f, "not matched"),
+      Outcome::Skipped => write!(f, "skipped"),
+      Outcome::Invalid => write!(f, "invalid"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceStep {
+  /// Where the rule is in the config, e.g. `window_rules[2]`.
+  pub rule: String,
+  pub outcome: Outcome,
+  /// Fields this rule set that no rule before it did, e.g. `active_border_color`.
+  pub contributed: Vec<String>,
+}
+
+/// Every rule in the order `resolve` looks at them, and what came out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trace {
+  pub steps: Vec<TraceStep>,
+  pub resolved: Resolved,
+}
+
+/// Goes through the rules in precedence order, every matching rule fills in
+/// what rules before it left unset. The first matching specific rule
+/// without `continue: true` ends the search for specific rules, Global rules
+/// always apply to whatever is still unset.
+pub fn resolve(rules: &[WindowRule], window: &dyn WindowFacts) -> Resolved {
+  evaluate(rules, window, |_, _, _, _| {})
+}
+
+/// Same as `resolve`, but records why each rule did or didn't apply.
+pub fn trace(rules: &[WindowRule], window: &dyn WindowFacts) -> Trace {
+  let mut steps = Vec::new();
+  let resolved = evaluate(rules, window, |rule, outcome, before, after| {
+    let mut contributed = Vec::new();
+    for (prefix, before, after) in [
+      ("active", &before.active, &after.active),
+      ("inactive", &before.inactive, &after.inactive),
+    ] {
+      for field in after.changed_fields(before) {
+        contributed.push(format!("{}_{}", prefix, field));
+      }
+    }
+    steps.push(TraceStep {
+      rule: rule.location.clone(),
+      outcome,
+      contributed,
+    });
+  });
+  Trace { steps, resolved }
+}
+
+/// Calls `visit` with every rule, its outcome and what was resolved
+/// before and after it.
+fn evaluate(
+  rules: &[WindowRule],
+  window: &dyn WindowFacts,
+  mut visit: impl FnMut(&WindowRule, Outcome, &Resolved, &Resolved),
+) -> Resolved {
+  let mut resolved = Resolved::default();
+  let mut stopped = false;
+
+  for rule in precedence_order(rules) {
+    let outcome = if rule.is_global() {
+      Outcome::Matched
+    } else {
+      match &rule.condition {
+        None => Outcome::Invalid,
+        Some(_) if stopped => Outcome::Skipped,
+        Some(condition) if condition.evaluate(window) => Outcome::Matched,
+        Some(_) => Outcome::NotMatched,
+      }
+    };
+
+    let before = resolved.clone();
+    if outcome == Outcome::Matched {
+      resolved.inherit(rule);
+      if !rule.is_global() {
+        stopped = !rule.fallthrough.unwrap_or(false);
+      }
+    }
+    visit(rule, outcome, &before, &resolved);
+  }
+
+  resolved
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::color::Color;
+  use crate::testing;
+
+  const CHROME: &str = r"C:\Program Files\Google\Chrome\Application\chrome.exe";
+  const SLACK: &str = r"C:\Users\me\AppData\Local\slack\app-4.41.105\slack.exe";
+
+  fn query(title: &str, class: &str, process_path: Option<&str>) -> WindowQuery {
+    WindowQuery {
+      title: title.to_string(),
+      class: class.to_string(),
+      process_path: process_path.map(str::to_string),
+    }
+  }
+
+  fn condition(yaml: &str) -> Result<Condition, String> {
+    Condition::compile(&serde_yaml::from_str(yaml).unwrap())
+  }
+
+  fn rule(yaml: &str) -> Result<Option<Condition>, String> {
+    compile_rule(&serde_yaml::from_str(yaml).unwrap())
+  }
+
+  fn rgb(hex: u32) -> Option<Color> {
+    Some(Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8))
+  }
+
+  #[test]
+  fn class_and_not_process() {
+    let condition = condition(
+      r#"
+all:
+  - class: "Chrome_WidgetWin_1"
+  - not:
+      process: "chrome.exe"
+"#,
+    )
+    .unwrap();
+
+    assert!(condition.evaluate(&query("Slack", "Chrome_WidgetWin_1", Some(SLACK))));
+    assert!(!condition.evaluate(&query("New Tab", "Chrome_WidgetWin_1", Some(CHROME))));
+    assert!(!condition.evaluate(&query("Untitled", "Notepad", Some(SLACK))));
+  }
+
+  #[test]
+  fn every_key_of_a_condition_has_to_match() {
+    let condition = condition("{ class: \"Chrome_WidgetWin_1\", title: \"Slack\" }").unwrap();
+    assert!(condition.evaluate(&query("Slack | general", "Chrome_WidgetWin_1", None)));
+    assert!(!condition.evaluate(&query("Discord", "Chrome_WidgetWin_1", None)));
+  }
+
+  #[test]
+  fn any_matches_if_one_does() {
+    let condition = condition(
+      r#"
+any:
+  - process: "slack.exe"
+  - title: "Discord"
+"#,
+    )
+    .unwrap();
+    assert!(condition.evaluate(&query("", "", Some(SLACK))));
+    assert!(condition.evaluate(&query("#general | Discord", "", None)));
+    assert!(!condition.evaluate(&query("New Tab", "", Some(CHROME))));
+  }
+
+  #[test]
+  fn unknown_processes_never_match() {
+    let process = condition("process: \"chrome.exe\"").unwrap();
+    let path = condition("path: \"chrome\"").unwrap();
+    let window = query("New Tab", "Chrome_WidgetWin_1", None);
+    assert!(!process.evaluate(&window));
+    assert!(!path.evaluate(&window));
+  }
+
+  #[test]
+  fn process_matches_the_file_name_and_path_the_whole_path() {
+    let process = condition("{ process: \"chrome.exe\", match_mode: \"exact\" }").unwrap();
+    let path = condition("{ path: \"C:\\\\Program Files\\\\*\", match_mode: \"glob\" }").unwrap();
+    let chrome = query("", "", Some(CHROME));
+    assert!(process.evaluate(&chrome));
+    assert!(path.evaluate(&chrome));
+    assert!(!path.evaluate(&query("", "", Some(SLACK))));
+  }
+
+  #[test]
+  fn match_modes() {
+    let exact = condition("{ title: \"notepad\", match_mode: \"exact\" }").unwrap();
+    assert!(exact.evaluate(&query("Notepad", "", None)));
+    assert!(!exact.evaluate(&query("Notepad++", "", None)));
+
+    let glob = condition("{ title: \"*.txt - Notepad\", match_mode: \"glob\" }").unwrap();
+    assert!(glob.evaluate(&query("todo.TXT - Notepad", "", None)));
+    assert!(!glob.evaluate(&query("todo.txt - Notepad++", "", None)));
+
+    // Regexes are the only ones that care about case.
+    let regex = condition("{ title: \"^\\\\d+ unread\", match_mode: \"regex\" }").unwrap();
+    assert!(regex.evaluate(&query("12 unread - Mail", "", None)));
+    assert!(!regex.evaluate(&query("No unread - Mail", "", None)));
+    let regex = condition("{ title: \"^Mail\", match_mode: \"regex\" }").unwrap();
+    assert!(!regex.evaluate(&query("mail", "", None)));
+  }
+
+  #[test]
+  fn broken_conditions_are_errors() {
+    assert_eq!(condition("{}").unwrap_err(), "Empty condition");
+    assert_eq!(condition("not: {}").unwrap_err(), "Empty condition");
+    let err = condition("{ title: \"(\", match_mode: \"regex\" }").unwrap_err();
+    assert!(err.starts_with("Invalid pattern `(`"), "{}", err);
+  }
+
+  #[test]
+  fn rules_need_either_match_or_when() {
+    assert!(rule("match: \"Global\"").unwrap().is_none());
+    assert!(rule("{ match: \"Title\", contains: \"Firefox\" }")
+      .unwrap()
+      .is_some());
+    assert!(rule("when: { title: \"Firefox\" }").unwrap().is_some());
+
+    assert_eq!(
+      rule("match: \"Title\"").unwrap_err(),
+      "Expected `contains` on `match: Title`"
+    );
+    assert_eq!(
+      rule("{ match: \"Title\", contains: \"a\", when: { title: \"b\" } }").unwrap_err(),
+      "`match` and `when` can't be used together"
+    );
+    assert_eq!(
+      rule("active_border_color: \"red\"").unwrap_err(),
+      "Expected either `match` or `when`"
+    );
+  }
+
+  const RULES: &str = r##"
+window_rules:
+  - match: "Global"
+    active_border_color: "#111111"
+    inactive_border_color: "#222222"
+    active_caption_color: "#333333"
+  - match: "Title"
+    contains: "Firefox"
+    active_border_color: "#ff0000"
+  - match: "Class"
+    contains: "MozillaWindowClass"
+    active_border_color: "#00ff00"
+    inactive_border_color: "#00ff00"
+  - match: "Process"
+    contains: "slack.exe"
+    priority: 1
+    active_border_color: "#0000ff"
+  - when:
+      process: "slack.exe"
+    active_border_color: "#ffffff"
+    inactive_border_color: "#ffffff"
+"##;
+
+  #[test]
+  fn first_matching_specific_rule_wins() {
+    let config = testing::config(RULES);
+    let resolved = resolve(
+      &config.window_rules,
+      &query("Mozilla Firefox", "MozillaWindowClass", None),
+    );
+    assert_eq!(resolved.active.border_color, rgb(0xff0000));
+    // The class rule never gets a say, Global fills in the rest.
+    assert_eq!(resolved.inactive.border_color, rgb(0x222222));
+    assert_eq!(resolved.active.caption_color, rgb(0x333333));
+    assert_eq!(resolved.inactive.caption_color, None);
+  }
+
+  #[test]
+  fn higher_priority_goes_first() {
+    let config = testing::config(RULES);
+    let resolved = resolve(&config.window_rules, &query("Slack", "", Some(SLACK)));
+    assert_eq!(resolved.active.border_color, rgb(0x0000ff));
+    // It doesn't continue, so the `when` rule is skipped.
+    assert_eq!(resolved.inactive.border_color, rgb(0x222222));
+  }
+
+  #[test]
+  fn continue_fills_in_from_later_rules() {
+    let config = testing::config(&RULES.replace(
+      "    contains: \"Firefox\"\n",
+      "    contains: \"Firefox\"\n    continue: true\n",
+    ));
+    let resolved = resolve(
+      &config.window_rules,
+      &query("Mozilla Firefox", "MozillaWindowClass", None),
+    );
+    assert_eq!(resolved.active.border_color, rgb(0xff0000));
+    assert_eq!(resolved.inactive.border_color, rgb(0x00ff00));
+  }
+
+  #[test]
+  fn unmatched_windows_only_get_global_rules() {
+    let config = testing::config(RULES);
+    let resolved = resolve(&config.window_rules, &query("Untitled", "Notepad", None));
+    assert_eq!(resolved.active.border_color, rgb(0x111111));
+    assert_eq!(resolved.inactive.border_color, rgb(0x222222));
+  }
+
+  const PRESENTING: &str = r#"
+active_profile: "presenting"
+profiles:
+  presenting:
+    window_rules:
+      - match: "Global"
+        active_border_color: "transparent"
+        inactive_border_color: "transparent"
+"#;
+
+  #[test]
+  fn profile_rules_win_over_main_ones() {
+    let _lock = testing::lock();
+    let config = testing::config(&format!("{}{}", RULES, PRESENTING));
+    let resolved = resolve(
+      &config.window_rules,
+      &query("Mozilla Firefox", "MozillaWindowClass", None),
+    );
+    assert_eq!(resolved.active.border_color, Some(Color::Transparent));
+    assert_eq!(resolved.inactive.border_color, Some(Color::Transparent));
+    // Whatever the profile leaves unset still comes from the main rules.
+    assert_eq!(resolved.active.caption_color, rgb(0x333333));
+
+    // `priority` still goes before everything else.
+    let resolved = resolve(&config.window_rules, &query("Slack", "", Some(SLACK)));
+    assert_eq!(resolved.active.border_color, rgb(0x0000ff));
+    assert_eq!(resolved.inactive.border_color, Some(Color::Transparent));
+  }
+
+  const TRACED: &str = r##"
+window_rules:
+  - match: "Global"
+    active_border_color: "#111111"
+    inactive_border_color: "#222222"
+    inactive_caption_color: "#444444"
+  - match: "Title"
+    contains: "Firefox"
+    continue: true
+    active_border_color: "#ff0000"
+  - match: "Process"
+    contains: "slack.exe"
+    active_border_color: "#0000ff"
+  - match: "Class"
+    contains: "("
+    match_mode: "regex"
+    active_border_color: "#0000ff"
+  - match: "Class"
+    contains: "MozillaWindowClass"
+    active_border_color: "#00ff00"
+    inactive_border_color: "#00ff00"
+    active_caption_color: "#333333"
+  - match: "Title"
+    contains: "Mozilla"
+    inactive_border_color: "#ffffff"
+  - match: "Global"
+    active_border_color: "#999999"
+"##;
+
+  fn steps(trace: &Trace) -> Vec<(&str, Outcome, Vec<&str>)> {
+    trace
+      .steps
+      .iter()
+      .map(|step| {
+        (
+          step.rule.as_str(),
+          step.outcome,
+          step.contributed.iter().map(String::as_str).collect(),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn trace_explains_every_rule() {
+    let config = testing::config(TRACED);
+    assert_eq!(config.errors.len(), 1);
+    let trace = trace(
+      &config.window_rules,
+      &query("Mozilla Firefox", "MozillaWindowClass", None),
+    );
+
+    assert_eq!(
+      steps(&trace),
+      vec![
+        (
+          "window_rules[1]",
+          Outcome::Matched,
+          vec!["active_border_color"]
+        ),
+        ("window_rules[2]", Outcome::NotMatched, vec![]),
+        ("window_rules[3]", Outcome::Invalid, vec![]),
+        // `continue` let it fill in what the first one left out.
+        (
+          "window_rules[4]",
+          Outcome::Matched,
+          vec!["active_caption_color", "inactive_border_color"]
+        ),
+        ("window_rules[5]", Outcome::Skipped, vec![]),
+        // Global rules only contribute what is still unset.
+        (
+          "window_rules[0]",
+          Outcome::Matched,
+          vec!["inactive_caption_color"]
+        ),
+        ("window_rules[6]", Outcome::Matched, vec![]),
+      ]
+    );
+    assert_eq!(
+      trace.resolved,
+      resolve(
+        &config.window_rules,
+        &query("Mozilla Firefox", "MozillaWindowClass", None)
+      )
+    );
+    assert_eq!(trace.resolved.active.border_color, rgb(0xff0000));
+    assert_eq!(trace.resolved.inactive.border_color, rgb(0x00ff00));
+  }
+
+  #[test]
+  fn trace_of_an_unmatched_window() {
+    let config = testing::config(TRACED);
+    let trace = trace(&config.window_rules, &query("Untitled", "Notepad", None));
+
+    let outcomes: Vec<Outcome> = trace.steps.iter().map(|step| step.outcome).collect();
+    assert_eq!(
+      outcomes,
+      vec![
+        Outcome::NotMatched,
+        Outcome::NotMatched,
+        Outcome::Invalid,
+        Outcome::NotMatched,
+        Outcome::NotMatched,
+        Outcome::Matched,
+        Outcome::Matched,
+      ]
+    );
+    assert_eq!(
+      trace.steps[5].contributed,
+      vec![
+        "active_border_color",
+        "inactive_border_color",
+        "inactive_caption_color"
+      ]
+    );
+    assert!(trace.steps[6].contributed.is_empty());
+  }
+}
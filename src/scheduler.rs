@@ -0,0 +1,289 @@
+use std::time::{Duration, Instant};
+
+use crate::backend::{EventSource, Hwnd, WindowBackend, WindowEvent};
+use crate::config::Config;
+use crate::overrides::Overrides;
+use crate::pause::Pause;
+use crate::rainbow::Rainbow;
+use crate::{apply_colors, apply_colors_to_window, forget_window};
+
+const ANIMATION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Recolors windows as events come in. Only while an animated color like
+/// `rainbow` is in use does it also wake up on a timer to redraw everything.
+pub struct Scheduler<'a> {
+  backend: &'a dyn WindowBackend,
+  events: &'a dyn EventSource,
+  foreground: Option<Hwnd>,
+  next_tick: Instant,
+  /// Whether windows were last reset for a pause or colored.
+  paused: bool,
+}
+
+impl<'a> Scheduler<'a> {
+  pub fn new(backend: &'a dyn WindowBackend, events: &'a dyn EventSource) -> Self {
+    Scheduler {
+      backend,
+      events,
+      foreground: backend.foreground_window(),
+      next_tick: Instant::now(),
+      paused: false,
+    }
+  }
+
+  pub fn run(&mut self) {
+    apply_colors(self.backend, false);
+    loop {
+      self.step();
+    }
+  }
+
+  /// Waits for a single event or animation tick and handles it.
+  pub fn step(&mut self) {
+    // Pausing and resuming send a refresh as well, this is for timed
+    // pauses that ran out.
+    let paused = Pause::is_paused();
+    if paused != self.paused {
+      self.paused = paused;
+      apply_colors(self.backend, paused);
+    }
+
+    // Nothing to animate while paused.
+    let animated = (Config::get().is_animated() || Overrides::is_animated()) && !paused;
+    let tick = animated.then(|| self.next_tick.saturating_duration_since(Instant::now()));
+    let timeout = match (tick, Pause::remaining()) {
+      (Some(tick), Some(remaining)) => Some(tick.min(remaining)),
+      (tick, remaining) => tick.or(remaining),
+    };
+
+    if let Some(event) = self.events.next_event(timeout) {
+      self.handle(event);
+    }
+
+    if animated && Instant::now() >= self.next_tick {
+      Rainbow::tick(Config::get().rainbow.speed.unwrap_or(1.0));
+      apply_colors(self.backend, false);
+      self.next_tick = Instant::now() + ANIMATION_INTERVAL;
+    }
+  }
+
+  pub fn handle(&mut self, event: WindowEvent) {
+    if Pause::is_paused() {
+      self.handle_paused(event);
+      return;
+    }
+    match event {
+      WindowEvent::Foreground(hwnd) => {
+        // The previous foreground window has to switch to its inactive color.
+        if let Some(previous) = self.foreground.replace(hwnd) {
+          if previous != hwnd {
+            self.recolor(previous);
+          }
+        }
+        self.recolor(hwnd);
+      }
+      WindowEvent::Shown(hwnd) | WindowEvent::NameChanged(hwnd) => self.recolor(hwnd),
+      WindowEvent::Destroyed(hwnd) => self.forget(hwnd),
+      WindowEvent::Refresh => apply_colors(self.backend, false),
+    }
+  }
+
+  // Pausing sends a refresh, which puts every window back to its defaults.
+  // New windows are left alone until resuming refreshes again.
+  fn handle_paused(&mut self, event: WindowEvent) {
+    match event {
+      WindowEvent::Foreground(hwnd) => self.foreground = Some(hwnd),
+      WindowEvent::Destroyed(hwnd) => self.forget(hwnd),
+      WindowEvent::Refresh => apply_colors(self.backend, true),
+      WindowEvent::Shown(_) | WindowEvent::NameChanged(_) => {}
+    }
+  }
+
+  fn forget(&mut self, hwnd: Hwnd) {
+    forget_window(hwnd);
+    if self.foreground == Some(hwnd) {
+      self.foreground = None;
+    }
+  }
+
+  fn recolor(&self, hwnd: Hwnd) {
+    if let Some(window) = self.backend.window_info(hwnd) {
+      let active = self.backend.foreground_window();
+      apply_colors_to_window(self.backend, &window, active, false);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::{FakeBackend, FakeEventSource};
+  use crate::color::Color;
+  use crate::process::ProcessCache;
+  use crate::testing::{self, window};
+  use crate::DWMWA_BORDER_COLOR;
+
+  const CONFIG: &str = r##"
+window_rules:
+  - match: "Global"
+    active_border_color: "accent"
+    inactive_border_color: "#0000ff"
+"##;
+  const ACCENT: u32 = 0x00123456;
+  const INACTIVE: u32 = 0x00ff0000;
+
+  fn backend() -> FakeBackend {
+    let backend = FakeBackend::default();
+    backend.add_window(window(1, 10, "Mozilla Firefox", "MozillaWindowClass"));
+    backend.add_window(window(2, 11, "Untitled", "Notepad"));
+    backend.set_foreground(Some(1));
+    backend.set_accent_color(Some(ACCENT));
+    backend
+  }
+
+  fn border(backend: &FakeBackend, hwnd: Hwnd) -> Option<u32> {
+    backend.attribute(hwnd, DWMWA_BORDER_COLOR)
+  }
+
+  #[test]
+  fn foreground_change_recolors_both_windows() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    let events = FakeEventSource::new();
+    let mut scheduler = Scheduler::new(&backend, &events);
+    apply_colors(&backend, false);
+    let calls = backend.attribute_calls();
+
+    backend.set_foreground(Some(2));
+    events.sender().send(WindowEvent::Foreground(2)).unwrap();
+    scheduler.step();
+
+    assert_eq!(border(&backend, 1), Some(INACTIVE));
+    assert_eq!(border(&backend, 2), Some(ACCENT));
+    assert_eq!(backend.attribute_calls(), calls + 2);
+  }
+
+  #[test]
+  fn new_windows_are_colored() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    let events = FakeEventSource::new();
+    let mut scheduler = Scheduler::new(&backend, &events);
+
+    backend.add_window(window(3, 12, "Calculator", "ApplicationFrameWindow"));
+    events.sender().send(WindowEvent::Shown(3)).unwrap();
+    scheduler.step();
+
+    assert_eq!(border(&backend, 3), Some(INACTIVE));
+    // Only the new window, nothing else was enumerated and recolored.
+    assert_eq!(border(&backend, 1), None);
+  }
+
+  #[test]
+  fn refresh_picks_up_accent_color_changes() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    let events = FakeEventSource::new();
+    let mut scheduler = Scheduler::new(&backend, &events);
+    apply_colors(&backend, false);
+
+    backend.set_accent_color(Some(0x00abcdef));
+    events.sender().send(WindowEvent::Refresh).unwrap();
+    scheduler.step();
+
+    assert_eq!(border(&backend, 1), Some(0x00abcdef));
+  }
+
+  #[test]
+  fn destroyed_windows_are_forgotten() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    let events = FakeEventSource::new();
+    let mut scheduler = Scheduler::new(&backend, &events);
+    Overrides::set(2, Some(Color::Rgb(0, 0, 0)));
+    backend.set_process_path(11, r"C:\Windows\notepad.exe");
+    ProcessCache::path(&backend, &window(2, 11, "Untitled", "Notepad"));
+
+    backend.remove_window(2);
+    events.sender().send(WindowEvent::Destroyed(2)).unwrap();
+    scheduler.step();
+
+    assert_eq!(Overrides::get(2), None);
+    // Its PID can belong to a different program now.
+    backend.set_process_path(11, r"C:\Windows\explorer.exe");
+    let reused = window(3, 11, "Documents", "CabinetWClass");
+    assert_eq!(
+      ProcessCache::path(&backend, &reused).as_deref(),
+      Some(r"C:\Windows\explorer.exe")
+    );
+  }
+
+  #[test]
+  fn pausing_resets_and_ignores_new_windows() {
+    let _lock = testing::lock();
+    testing::set_config(CONFIG);
+    let backend = backend();
+    let events = FakeEventSource::new();
+    let mut scheduler = Scheduler::new(&backend, &events);
+    apply_colors(&backend, false);
+
+    Pause::pause(None);
+    events.sender().send(WindowEvent::Refresh).unwrap();
+    scheduler.step();
+    assert_eq!(border(&backend, 1), Some(crate::DWMWA_COLOR_DEFAULT));
+
+    backend.add_window(window(3, 12, "Calculator", "ApplicationFrameWindow"));
+    events.sender().send(WindowEvent::Shown(3)).unwrap();
+    scheduler.step();
+    assert_eq!(border(&backend, 3), None);
+
+    Pause::resume();
+    events.sender().send(WindowEvent::Refresh).unwrap();
+    scheduler.step();
+    assert_eq!(border(&backend, 1), Some(ACCENT));
+    assert_eq!(border(&backend, 3), Some(INACTIVE));
+  }
+
+  #[test]
+  fn animated_colors_redraw_without_events() {
+    let _lock = testing::lock();
+    testing::set_config(
+      r##"
+rainbow:
+  speed: 30
+window_rules:
+  - match: "Global"
+    active_border_color: "rainbow"
+"##,
+    );
+    let backend = backend();
+    let events = FakeEventSource::new();
+    let mut scheduler = Scheduler::new(&backend, &events);
+
+    scheduler.step();
+    let first = border(&backend, 1);
+    scheduler.step();
+    assert!(first.is_some());
+    assert_ne!(border(&backend, 1), first);
+  }
+
+  #[test]
+  fn rainbow_overrides_redraw_without_events() {
+    let _lock = testing::lock();
+    testing::set_config(&format!("rainbow:\n  speed: 30\n{}", CONFIG));
+    let backend = backend();
+    let events = FakeEventSource::new();
+    let mut scheduler = Scheduler::new(&backend, &events);
+    Overrides::set(1, Some(Color::Rainbow));
+
+    scheduler.step();
+    let first = border(&backend, 1);
+    scheduler.step();
+    assert!(first.is_some());
+    assert_ne!(border(&backend, 1), first);
+  }
+}
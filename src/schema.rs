@@ -0,0 +1,127 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::{schema_for, JsonSchema};
+use serde_json::json;
+
+use crate::color::{color_names, Color};
+use crate::config::Config;
+use crate::logger::Logger;
+use crate::palette::theme_names;
+use crate::util::get_file_path;
+
+/// JSON Schema for config.yaml, generated from the config types.
+pub fn config_schema() -> String {
+  serde_json::to_string_pretty(&schema_for!(Config)).expect("Failed to serialize schema")
+}
+
+/// Written next to config.yaml on every start so editors using
+/// yaml-language-server always validate against this version.
+pub fn write_schema_file() {
+  let path = get_file_path("config.schema.json");
+  if let Err(err) = std::fs::write(&path, config_schema()) {
+    Logger::log(&format!("[ERROR] Failed to write {}", path));
+    Logger::log(&format!("[DEBUG] {:?}", err));
+  }
+}
+
+// Colors are plain strings in the config, this describes what `Color::from_str`
+// and `$name` lookups accept. Patterns only cover lowercase, the parser
+// itself doesn't care about case.
+impl JsonSchema for Color {
+  fn schema_name() -> String {
+    "Color".to_string()
+  }
+
+  fn json_schema(_: &mut SchemaGenerator) -> Schema {
+    let mut names = vec!["default", "transparent", "accent", "rainbow"];
+    names.extend(color_names());
+
+    from_json(json!({
+      "description": "A color: #rgb, #rrggbb, rgb(), hsl(), oklch(), a CSS color name, \
+        $name from colors/theme/palette_file, accent, rainbow, default or transparent",
+      "type": "string",
+      "anyOf": [
+        { "enum": names },
+        { "pattern": "^#([0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$" },
+        { "pattern": "^(rgba?|hsla?|oklch)\\s*\\(.*\\)$" },
+        { "pattern": "^\\$.+$" }
+      ]
+    }))
+  }
+}
+
+/// Name of a bundled theme for `theme:`.
+pub struct ThemeName;
+
+impl JsonSchema for ThemeName {
+  fn schema_name() -> String {
+    "ThemeName".to_string()
+  }
+
+  fn json_schema(_: &mut SchemaGenerator) -> Schema {
+    let names: Vec<&str> = theme_names().collect();
+    from_json(json!({ "type": "string", "enum": names }))
+  }
+}
+
+fn from_json(value: serde_json::Value) -> Schema {
+  serde_json::from_value(value).expect("Invalid schema")
+}
+
+#[cfg(test)]
+mod tests {
+  use jsonschema::JSONSchema;
+  use serde_json::Value;
+
+  use super::*;
+  use crate::color::Color;
+
+  fn errors(yaml: &str) -> Vec<String> {
+    let schema = serde_json::from_str(&config_schema()).unwrap();
+    let schema = JSONSchema::compile(&schema).expect("Schema is invalid");
+    let config: Value = serde_yaml::from_str(yaml).unwrap();
+    let errors = match schema.validate(&config) {
+      Ok(()) => Vec::new(),
+      Err(errors) => errors
+        .map(|err| format!("{} at {}", err, err.instance_path))
+        .collect(),
+    };
+    errors
+  }
+
+  #[test]
+  fn default_config_is_valid() {
+    assert_eq!(
+      errors(include_str!("data/config.yaml")),
+      Vec::<String>::new()
+    );
+  }
+
+  #[test]
+  fn hex_colors_match_what_parses() {
+    let rule = |color: &str| {
+      errors(&format!(
+        "window_rules:\n  - match: \"Global\"\n    active_border_color: \"{}\"\n",
+        color
+      ))
+    };
+    for color in ["#fff", "#ffff", "#ffffff", "#ffffff80"] {
+      assert_eq!(rule(color), Vec::<String>::new(), "{}", color);
+      assert!(color.parse::<Color>().is_ok(), "{}", color);
+    }
+    for color in ["#ff", "#fffff", "#fffffff", "#+f+f+f"] {
+      assert!(!rule(color).is_empty(), "{}", color);
+      assert!(color.parse::<Color>().is_err(), "{}", color);
+    }
+  }
+
+  #[test]
+  fn mistakes_are_reported() {
+    // Moved to `tray.hide_icon` in version 2
+    assert!(!errors("hide_tray_icon: false\n").is_empty());
+    assert!(
+      !errors("window_rules:\n  - match: \"Global\"\n    active_border_color: \"#12\"\n")
+        .is_empty()
+    );
+  }
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::logger::Logger;
+use crate::util::get_file_path;
+
+/// Things changed at runtime that should survive a restart, kept in
+/// state.yaml next to config.yaml so the config itself is never rewritten.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct State {
+  /// Picked from the tray, `None` means no profile.
+  pub active_profile: Option<String>,
+}
+
+impl State {
+  /// `None` if nothing was ever saved, or the file is broken.
+  pub fn load() -> Option<Self> {
+    let contents = fs::read_to_string(get_file_path("state.yaml")).ok()?;
+    match serde_yaml::from_str(&contents) {
+      Ok(state) => Some(state),
+      Err(err) => {
+        Logger::log("[ERROR] Failed to parse state.yaml, ignoring it");
+        Logger::log(&format!("[DEBUG] {:?}", err));
+        None
+      }
+    }
+  }
+
+  pub fn save(&self) {
+    let result = serde_yaml::to_string(self)
+      .map_err(|err| format!("{:?}", err))
+      .and_then(|contents| {
+        fs::write(get_file_path("state.yaml"), contents).map_err(|err| format!("{:?}", err))
+      });
+    if let Err(err) = result {
+      Logger::log("[ERROR] Failed to save state.yaml");
+      Logger::log(&format!("[DEBUG] {}", err));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Config;
+  use crate::testing;
+
+  const CONFIG: &str = r##"
+active_profile: "work"
+window_rules:
+  - match: "Global"
+    active_border_color: "#ff0000"
+profiles:
+  work:
+    window_rules: []
+  presenting:
+    window_rules: []
+"##;
+
+  fn save(active_profile: Option<&str>) {
+    State {
+      active_profile: active_profile.map(str::to_string),
+    }
+    .save();
+  }
+
+  #[test]
+  fn picked_profile_is_used_on_the_next_load() {
+    let _lock = testing::lock();
+    assert_eq!(State::load(), None);
+    assert_eq!(testing::config(CONFIG).profile.as_deref(), Some("work"));
+
+    save(Some("presenting"));
+    assert_eq!(
+      fs::read_to_string(get_file_path("state.yaml")).unwrap(),
+      "active_profile: presenting\n"
+    );
+    assert_eq!(
+      State::load().unwrap().active_profile.as_deref(),
+      Some("presenting")
+    );
+    assert_eq!(
+      testing::config(CONFIG).profile.as_deref(),
+      Some("presenting")
+    );
+  }
+
+  #[test]
+  fn no_profile_wins_over_active_profile() {
+    let _lock = testing::lock();
+    fs::write(get_file_path("config.yaml"), CONFIG).unwrap();
+    assert!(Config::reload());
+    assert_eq!(Config::get().profile.as_deref(), Some("work"));
+
+    assert!(Config::set_profile(None));
+    assert_eq!(Config::get().profile, None);
+    assert_eq!(testing::config(CONFIG).profile, None);
+  }
+
+  #[test]
+  fn broken_state_is_ignored() {
+    let _lock = testing::lock();
+    fs::write(get_file_path("state.yaml"), "active_profile: [").unwrap();
+    assert_eq!(State::load(), None);
+    assert_eq!(testing::config(CONFIG).profile.as_deref(), Some("work"));
+  }
+}
@@ -0,0 +1,86 @@
+// Shared setup for tests. Most of cute-borders keeps its state in globals
+// (the config, caches, pause...), so tests that touch them take turns.
+
+use std::cell::Cell;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, Once};
+
+use crate::backend::{Hwnd, WindowInfo};
+use crate::cache::ColorCache;
+use crate::config::Config;
+use crate::overrides::Overrides;
+use crate::pause::Pause;
+use crate::process::ProcessCache;
+use crate::util::get_file_path;
+
+static LOCK: Mutex<()> = Mutex::new(());
+static HOME: Once = Once::new();
+
+thread_local! {
+  static LOCKED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Held by `lock`, lets `config` know it doesn't have to take turns itself.
+pub struct Lock {
+  _guard: MutexGuard<'static, ()>,
+}
+
+impl Drop for Lock {
+  fn drop(&mut self) {
+    LOCKED.with(|locked| locked.set(false));
+  }
+}
+
+fn take_turn() -> Lock {
+  home();
+  // A failed test doesn't leave anything behind that the reset can't fix.
+  let guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+  LOCKED.with(|locked| locked.set(true));
+  Lock { _guard: guard }
+}
+
+/// Points the user profile at a temporary folder, so tests never read or
+/// write the real .cuteborders.
+pub fn home() -> PathBuf {
+  let home = std::env::temp_dir().join(format!("cute-borders-tests-{}", std::process::id()));
+  HOME.call_once(|| {
+    fs::create_dir_all(&home).expect("Failed to create test home");
+    std::env::set_var("USERPROFILE", &home);
+    std::env::set_var("HOME", &home);
+  });
+  home
+}
+
+/// Has to be held by tests that use globals, which start out empty.
+pub fn lock() -> Lock {
+  let lock = take_turn();
+  let _ = fs::remove_file(get_file_path("state.yaml"));
+  ColorCache::clear();
+  Overrides::clear();
+  ProcessCache::clear();
+  Pause::resume();
+  lock
+}
+
+/// Parses `contents` like config.yaml, without touching the running config.
+/// Parsing reads state.yaml, so it waits for tests that hold `lock`.
+pub fn config(contents: &str) -> Config {
+  let _lock = (!LOCKED.with(Cell::get)).then(take_turn);
+  Config::parse(contents).expect("Invalid test config")
+}
+
+/// Swaps in `contents` as the running config, needs `lock`.
+pub fn set_config(contents: &str) {
+  Config::set(config(contents));
+}
+
+pub fn window(hwnd: Hwnd, pid: u32, title: &str, class: &str) -> WindowInfo {
+  WindowInfo {
+    hwnd,
+    pid,
+    title: title.to_string(),
+    class: class.to_string(),
+    ex_style: 0,
+  }
+}
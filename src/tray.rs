@@ -0,0 +1,464 @@
+use check_elevation::is_elevated;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use tray_icon::menu::CheckMenuItem;
+use tray_icon::menu::Menu;
+use tray_icon::menu::MenuEvent;
+use tray_icon::menu::MenuId;
+use tray_icon::menu::MenuItem;
+use tray_icon::menu::MenuItemBuilder;
+use tray_icon::menu::PredefinedMenuItem;
+use tray_icon::menu::Submenu;
+use tray_icon::Icon;
+use tray_icon::TrayIcon;
+use tray_icon::TrayIconBuilder;
+use tray_icon::TrayIconEvent;
+use winapi::um::shellapi::ShellExecuteExW;
+use winapi::um::shellapi::SEE_MASK_NOASYNC;
+use winapi::um::shellapi::SEE_MASK_NOCLOSEPROCESS;
+use winapi::um::shellapi::SHELLEXECUTEINFOW;
+use winapi::um::winuser::{
+  DispatchMessageW, GetMessageW, RegisterHotKey, SetTimer, TranslateMessage, UnregisterHotKey,
+  MOD_NOREPEAT, WM_HOTKEY, WM_TIMER,
+};
+
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use crate::apply_colors;
+use crate::backend::{PlatformBackend, WindowEvent, WindowInfo};
+use crate::config::Config;
+use crate::hotkey::Hotkey;
+use crate::inventory::colored_windows;
+use crate::logger::Logger;
+use crate::new_rule::{self, rule_for, RuleTarget};
+use crate::notify;
+use crate::pause::Pause;
+use crate::process::{file_name, ProcessCache};
+use crate::startup::get_exe_path;
+use crate::startup::set_startup;
+use crate::util::get_file_path;
+
+const PROFILE_ID_PREFIX: &str = "profile:";
+const PAUSE_ID: &str = "pause";
+/// Followed by the number of minutes.
+const PAUSE_FOR_ID_PREFIX: &str = "pause:";
+/// Followed by `RuleTarget::to_id`.
+const NEW_RULE_ID_PREFIX: &str = "new-rule:";
+/// Resource ids, see build.rs.
+const ICON: u16 = 1;
+const PAUSED_ICON: u16 = 2;
+/// The only hotkey cute-borders registers.
+const PAUSE_HOTKEY_ID: i32 = 1;
+
+/// A profile's menu item, `None` is the one for no profile.
+type ProfileItem = (Option<String>, CheckMenuItem);
+
+pub fn run(events: Sender<WindowEvent>) {
+  let backend = PlatformBackend::default();
+  let is_elevated = is_elevated().unwrap_or(false);
+  let mut profile_items: Vec<ProfileItem> = Vec::new();
+  let pause_item = CheckMenuItem::with_id(PAUSE_ID, "Pause borders", true, false, None);
+  let mut shown_paused = false;
+  let new_rule_menu = Submenu::new("Create rule for window", true);
+  update_new_rule_menu(&new_rule_menu, &backend);
+  unsafe {
+    let mut tray_icon: Option<TrayIcon> = None; // needs to be in the main scope
+    if !Config::get().tray.hide_icon.unwrap_or(false) {
+      let pause_for_menu = Submenu::with_items(
+        "Pause for",
+        true,
+        &[
+          &MenuItem::with_id(
+            format!("{}15", PAUSE_FOR_ID_PREFIX),
+            "15 minutes",
+            true,
+            None,
+          ),
+          &MenuItem::with_id(format!("{}60", PAUSE_FOR_ID_PREFIX), "1 hour", true, None),
+        ],
+      );
+      let pause_for_menu = match pause_for_menu {
+        Ok(pause_for_menu) => pause_for_menu,
+        Err(err) => {
+          Logger::log("[ERROR] Failed to build tray icon");
+          Logger::log(&format!("[DEBUG] {:?}", err));
+          std::process::exit(1);
+        }
+      };
+
+      let tray_menu_builder = Menu::with_items(&[
+        &MenuItemBuilder::new()
+          .text("Open config")
+          .enabled(true)
+          .id(MenuId::new("0"))
+          .build(),
+        &MenuItemBuilder::new()
+          .text("Reload config")
+          .enabled(true)
+          .id(MenuId::new("1"))
+          .build(),
+        &pause_item,
+        &pause_for_menu,
+        &new_rule_menu,
+        &MenuItemBuilder::new()
+          .text(if is_elevated { "Uninstall" } else { "Install" })
+          .enabled(true)
+          .id(MenuId::new("2"))
+          .build(),
+        &MenuItemBuilder::new()
+          .text("Exit")
+          .enabled(true)
+          .id(MenuId::new("3"))
+          .build(),
+      ]);
+
+      let tray_menu = match tray_menu_builder {
+        Ok(tray_menu) => tray_menu,
+        Err(err) => {
+          Logger::log("[ERROR] Failed to build tray icon");
+          Logger::log(&format!("[DEBUG] {:?}", err));
+          std::process::exit(1);
+        }
+      };
+
+      let config = Config::get();
+      let profiles = config.profile_names();
+      if !profiles.is_empty() {
+        let profile_menu = Submenu::new("Profile", true);
+        for profile in [None].into_iter().chain(profiles.into_iter().map(Some)) {
+          let item = CheckMenuItem::with_id(
+            format!(
+              "{}{}",
+              PROFILE_ID_PREFIX,
+              profile.as_deref().unwrap_or_default()
+            ),
+            profile.as_deref().unwrap_or("None"),
+            true,
+            config.profile == profile,
+            None,
+          );
+          if let Err(err) = profile_menu.append(&item) {
+            Logger::log("[ERROR] Failed to build tray icon");
+            Logger::log(&format!("[DEBUG] {:?}", err));
+            std::process::exit(1);
+          }
+          profile_items.push((profile, item));
+        }
+        // Right after "Reload config"
+        if let Err(err) = tray_menu.insert(&profile_menu, 2) {
+          Logger::log("[ERROR] Failed to build tray icon");
+          Logger::log(&format!("[DEBUG] {:?}", err));
+          std::process::exit(1);
+        }
+      }
+
+      let icon = match Icon::from_resource(ICON, Some((64, 64))) {
+        Ok(icon) => icon,
+        Err(err) => {
+          Logger::log("[ERROR] Failed to create icon");
+          Logger::log(&format!("[DEBUG] {:?}", err));
+          std::process::exit(1);
+        }
+      };
+
+      let tray_icon_builder = TrayIconBuilder::new()
+        .with_menu(Box::new(tray_menu))
+        .with_menu_on_left_click(true)
+        .with_icon(icon)
+        .with_tooltip(tooltip(false));
+
+      tray_icon = match tray_icon_builder.build() {
+        Ok(tray_icon) => Some(tray_icon),
+        Err(err) => {
+          Logger::log("[ERROR] Failed to build tray icon");
+          Logger::log(&format!("[DEBUG] {:?}", err));
+          std::process::exit(1);
+        }
+      };
+    }
+
+    // Wakes the loop up every second, so the icon also changes when a pause
+    // runs out or comes in over IPC.
+    SetTimer(std::ptr::null_mut(), 0, 1000, None);
+    let mut pause_hotkey = update_pause_hotkey(None);
+
+    let mut msg = std::mem::zeroed();
+    while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) != 0 {
+      TranslateMessage(&msg);
+      DispatchMessageW(&msg);
+      // Both are posted to this thread instead of a window.
+      if msg.hwnd.is_null() {
+        if msg.message == WM_HOTKEY && msg.wParam == PAUSE_HOTKEY_ID as usize {
+          toggle_pause(&events);
+        } else if msg.message == WM_TIMER {
+          pause_hotkey = update_pause_hotkey(pause_hotkey);
+        }
+      }
+      // Handled here instead of with MenuEvent::set_event_handler since
+      // menu items can't be moved to the thread that handler runs on.
+      while let Ok(event) = MenuEvent::receiver().try_recv() {
+        handle_menu_event(&event, &backend, &events, is_elevated, &profile_items);
+      }
+
+      while let Ok(event) = TrayIconEvent::receiver().try_recv() {
+        // The mouse always enters the icon before it can be clicked, so the
+        // list is up to date by the time the menu opens.
+        if let TrayIconEvent::Enter { .. } = event {
+          update_new_rule_menu(&new_rule_menu, &backend);
+        }
+      }
+
+      let paused = Pause::is_paused();
+      if paused != shown_paused {
+        shown_paused = paused;
+        show_paused(tray_icon.as_ref(), &pause_item, paused);
+      }
+    }
+
+    apply_colors(&backend, true);
+  }
+}
+
+fn handle_menu_event(
+  event: &MenuEvent,
+  backend: &PlatformBackend,
+  events: &Sender<WindowEvent>,
+  is_elevated: bool,
+  profile_items: &[ProfileItem],
+) {
+  if event.id == MenuId::new("0") {
+    let _ = open::that(get_file_path("config.yaml"));
+  } else if event.id == MenuId::new("1") {
+    if Config::reload() {
+      let _ = events.send(WindowEvent::Refresh);
+    }
+    update_profile_checks(profile_items);
+  } else if event.id == MenuId::new("2") {
+    if is_elevated {
+      if let Err(err) = set_startup(false) {
+        Logger::log("[ERROR] Failed to create or update startup task");
+        Logger::log(&format!("[DEBUG] {:?}", err));
+      }
+      apply_colors(backend, true);
+      std::process::exit(0);
+    } else {
+      let lp_verb: Vec<u16> = OsStr::new("runas")
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+      let d = get_exe_path();
+      let v = d.to_str().unwrap_or_default();
+      let lp_file: Vec<u16> = OsStr::new(&v)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+      let lp_par: Vec<u16> = OsStr::new("")
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+      let mut sei = SHELLEXECUTEINFOW {
+        cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOASYNC | SEE_MASK_NOCLOSEPROCESS,
+        lpVerb: lp_verb.as_ptr(),
+        lpFile: lp_file.as_ptr(),
+        lpParameters: lp_par.as_ptr(),
+        nShow: 1,
+        dwHotKey: 0,
+        hInstApp: std::ptr::null_mut(),
+        hMonitor: std::ptr::null_mut(),
+        hProcess: std::ptr::null_mut(),
+        hkeyClass: std::ptr::null_mut(),
+        hwnd: std::ptr::null_mut(),
+        lpClass: std::ptr::null_mut(),
+        lpDirectory: std::ptr::null_mut(),
+        lpIDList: std::ptr::null_mut(),
+      };
+
+      unsafe { ShellExecuteExW(&mut sei) };
+      apply_colors(backend, true);
+      std::process::exit(0);
+    }
+  } else if event.id == MenuId::new("3") {
+    apply_colors(backend, true);
+    std::process::exit(0);
+  } else if let Some(target) = event
+    .id
+    .0
+    .strip_prefix(NEW_RULE_ID_PREFIX)
+    .and_then(RuleTarget::from_id)
+  {
+    match new_rule::add_to_config(&target) {
+      Ok(()) => {
+        if Config::reload() {
+          let _ = events.send(WindowEvent::Refresh);
+        }
+      }
+      Err(err) => {
+        Logger::log(&format!("[ERROR] {}", err));
+        notify::show_error(&err);
+      }
+    }
+  } else if event.id == MenuId::new(PAUSE_ID) {
+    toggle_pause(events);
+  } else if let Some(minutes) = event.id.0.strip_prefix(PAUSE_FOR_ID_PREFIX) {
+    if let Ok(minutes) = minutes.parse::<u64>() {
+      Pause::pause(Some(Duration::from_secs(minutes * 60)));
+      let _ = events.send(WindowEvent::Refresh);
+    }
+  } else if let Some(profile) = event.id.0.strip_prefix(PROFILE_ID_PREFIX) {
+    let profile = Some(profile).filter(|profile| !profile.is_empty());
+    if Config::set_profile(profile) {
+      let _ = events.send(WindowEvent::Refresh);
+    }
+    update_profile_checks(profile_items);
+  }
+}
+
+fn toggle_pause(events: &Sender<WindowEvent>) {
+  if Pause::is_paused() {
+    Pause::resume();
+  } else {
+    Pause::pause(None);
+  }
+  let _ = events.send(WindowEvent::Refresh);
+}
+
+// Called every second so a changed `tray.pause_hotkey` is picked up after a
+// reload. A hotkey another program already has is logged once and not
+// tried again until the config changes it.
+fn update_pause_hotkey(registered: Option<Hotkey>) -> Option<Hotkey> {
+  // Invalid ones are reported with the other config errors.
+  let hotkey = Config::get().tray.pause_hotkey().ok().flatten();
+  if hotkey == registered {
+    return registered;
+  }
+  unsafe {
+    UnregisterHotKey(std::ptr::null_mut(), PAUSE_HOTKEY_ID);
+    if let Some(hotkey) = hotkey {
+      let modifiers = hotkey.modifiers | MOD_NOREPEAT as u32;
+      if RegisterHotKey(std::ptr::null_mut(), PAUSE_HOTKEY_ID, modifiers, hotkey.key) == 0 {
+        Logger::log("[ERROR] Failed to register the pause hotkey, another program might use it");
+        Logger::log(&format!("[DEBUG] {:?}", std::io::Error::last_os_error()));
+      }
+    }
+  }
+  hotkey
+}
+
+// One submenu per process, with an item for a rule matching the process
+// and one for each of its windows, which matches the window's class.
+fn update_new_rule_menu(menu: &Submenu, backend: &PlatformBackend) {
+  while menu.remove_at(0).is_some() {}
+
+  let mut processes: BTreeMap<String, Vec<WindowInfo>> = BTreeMap::new();
+  for window in colored_windows(backend) {
+    let process = ProcessCache::path(backend, &window)
+      .map(|path| file_name(&path).to_string())
+      .unwrap_or_default();
+    processes.entry(process).or_default().push(window);
+  }
+
+  let result = if processes.is_empty() {
+    menu.append(&MenuItem::new("No windows", false, None))
+  } else {
+    processes
+      .iter()
+      .try_for_each(|(process, windows)| append_process_menu(menu, process, windows))
+  };
+  if let Err(err) = result {
+    Logger::log("[ERROR] Failed to list windows in tray menu");
+    Logger::log(&format!("[DEBUG] {:?}", err));
+  }
+}
+
+fn append_process_menu(
+  menu: &Submenu,
+  process: &str,
+  windows: &[WindowInfo],
+) -> tray_icon::menu::Result<()> {
+  let id = |target: RuleTarget| format!("{}{}", NEW_RULE_ID_PREFIX, target.to_id());
+  let process_menu = Submenu::new(
+    menu_text(if process.is_empty() {
+      "Unknown process"
+    } else {
+      process
+    }),
+    true,
+  );
+  if let Some(target) = rule_for(process, None) {
+    process_menu.append(&MenuItem::with_id(
+      id(target),
+      format!("All {} windows", menu_text(process)),
+      true,
+      None,
+    ))?;
+    process_menu.append(&PredefinedMenuItem::separator())?;
+  }
+  for window in windows {
+    let Some(target) = rule_for(process, Some(window)) else {
+      continue;
+    };
+    process_menu.append(&MenuItem::with_id(
+      id(target),
+      format!(
+        "{} ({})",
+        menu_text(&window.title),
+        menu_text(&window.class)
+      ),
+      true,
+      None,
+    ))?;
+  }
+  menu.append(&process_menu)
+}
+
+// Window titles can be long and `&` would underline the next letter.
+fn menu_text(text: &str) -> String {
+  const MAX_LENGTH: usize = 60;
+  let mut short: String = text.chars().take(MAX_LENGTH).collect();
+  if short.len() < text.len() {
+    short.push_str("...");
+  }
+  short.replace('&', "&&")
+}
+
+fn tooltip(paused: bool) -> String {
+  let tooltip = format!("cute-borders v{}", env!("CARGO_PKG_VERSION"));
+  match paused {
+    true => format!("{} (paused)", tooltip),
+    false => tooltip,
+  }
+}
+
+// Grays out the icon while paused. Also puts the check on "Pause borders"
+// back in sync, clicking it toggles it even if the pause came from IPC.
+fn show_paused(tray_icon: Option<&TrayIcon>, pause_item: &CheckMenuItem, paused: bool) {
+  pause_item.set_checked(paused);
+  let Some(tray_icon) = tray_icon else {
+    return;
+  };
+  let result = Icon::from_resource(if paused { PAUSED_ICON } else { ICON }, Some((64, 64)))
+    .map_err(|err| format!("{:?}", err))
+    .and_then(|icon| {
+      tray_icon
+        .set_icon(Some(icon))
+        .map_err(|err| format!("{:?}", err))
+    });
+  if let Err(err) = result {
+    Logger::log("[ERROR] Failed to change tray icon");
+    Logger::log(&format!("[DEBUG] {}", err));
+  }
+  let _ = tray_icon.set_tooltip(Some(tooltip(paused)));
+}
+
+// Clicking a check item toggles it by itself, this puts the check back
+// on whatever profile is actually in use.
+fn update_profile_checks(profile_items: &[ProfileItem]) {
+  let active = Config::get().profile;
+  for (profile, item) in profile_items {
+    item.set_checked(*profile == active);
+  }
+}
@@ -0,0 +1,265 @@
+use std::fs;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::backend::WindowEvent;
+use crate::config::Config;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Editors often write a file several times when saving, wait until
+/// it has been quiet for this long before reloading.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Time and file metadata, kept behind a trait so the debouncing can be
+/// driven by a fake clock.
+pub trait WatchBackend {
+  fn now(&self) -> Instant;
+  /// `None` if the file doesn't exist (yet).
+  fn modified(&self, path: &str) -> Option<SystemTime>;
+}
+
+#[derive(Default)]
+pub struct SystemWatchBackend;
+
+impl WatchBackend for SystemWatchBackend {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+
+  fn modified(&self, path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+  }
+}
+
+pub struct Watcher<'a> {
+  backend: &'a dyn WatchBackend,
+  files: Vec<String>,
+  modified: Vec<Option<SystemTime>>,
+  /// When a file last changed, while waiting for the debounce to pass.
+  changed_at: Option<Instant>,
+}
+
+impl<'a> Watcher<'a> {
+  pub fn new(backend: &'a dyn WatchBackend, files: Vec<String>) -> Self {
+    let mut watcher = Watcher {
+      backend,
+      files: Vec::new(),
+      modified: Vec::new(),
+      changed_at: None,
+    };
+    watcher.set_files(files);
+    watcher
+  }
+
+  /// Starts watching `files` from their current state, e.g. after a reload
+  /// pointed the config at a different palette file.
+  pub fn set_files(&mut self, files: Vec<String>) {
+    self.modified = self.modified_times(&files);
+    self.files = files;
+    self.changed_at = None;
+  }
+
+  /// `true` once something changed and then stayed the same for `DEBOUNCE`.
+  pub fn poll(&mut self) -> bool {
+    let now = self.backend.now();
+    let modified = self.modified_times(&self.files);
+    if modified != self.modified {
+      self.modified = modified;
+      self.changed_at = Some(now);
+      return false;
+    }
+
+    match self.changed_at {
+      Some(changed_at) if now.duration_since(changed_at) >= DEBOUNCE => {
+        self.changed_at = None;
+        true
+      }
+      _ => false,
+    }
+  }
+
+  fn modified_times(&self, files: &[String]) -> Vec<Option<SystemTime>> {
+    files
+      .iter()
+      .map(|file| self.backend.modified(file))
+      .collect()
+  }
+}
+
+/// Reloads the config and recolors everything whenever config.yaml or one
+/// of the files it uses changes, e.g. when pywal regenerates its colors.
+pub fn watch(events: Sender<WindowEvent>) {
+  thread::spawn(move || {
+    let backend = SystemWatchBackend;
+    let mut watcher = Watcher::new(&backend, Config::get().watched_files());
+    loop {
+      thread::sleep(POLL_INTERVAL);
+      if !reload_if_changed(&mut watcher, &events) {
+        return;
+      }
+    }
+  });
+}
+
+/// `false` once nothing listens for the refresh anymore.
+fn reload_if_changed(watcher: &mut Watcher, events: &Sender<WindowEvent>) -> bool {
+  if !watcher.poll() {
+    return true;
+  }
+  // A broken config is logged and the old one stays, nothing to redraw.
+  if Config::reload() && events.send(WindowEvent::Refresh).is_err() {
+    return false;
+  }
+  watcher.set_files(Config::get().watched_files());
+  true
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::cell::{Cell, RefCell};
+  use std::collections::HashMap;
+  use std::sync::mpsc::channel;
+
+  use crate::color::Color;
+  use crate::testing;
+  use crate::util::get_file_path;
+
+  /// Time only moves when told to, files only change when told to.
+  struct FakeWatchBackend {
+    now: Cell<Instant>,
+    modified: RefCell<HashMap<String, SystemTime>>,
+  }
+
+  impl FakeWatchBackend {
+    fn new() -> Self {
+      FakeWatchBackend {
+        now: Cell::new(Instant::now()),
+        modified: RefCell::new(HashMap::new()),
+      }
+    }
+
+    fn advance(&self, millis: u64) {
+      self.now.set(self.now.get() + Duration::from_millis(millis));
+    }
+
+    // Any time that differs from the last one will do.
+    fn touch(&self, path: &str) {
+      let mut modified = self.modified.borrow_mut();
+      let time = match modified.get(path) {
+        Some(time) => *time + Duration::from_secs(1),
+        None => SystemTime::UNIX_EPOCH,
+      };
+      modified.insert(path.to_string(), time);
+    }
+  }
+
+  impl WatchBackend for FakeWatchBackend {
+    fn now(&self) -> Instant {
+      self.now.get()
+    }
+
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+      self.modified.borrow().get(path).copied()
+    }
+  }
+
+  #[test]
+  fn burst_of_writes_reloads_once() {
+    let backend = FakeWatchBackend::new();
+    backend.touch("config.yaml");
+    let mut watcher = Watcher::new(&backend, vec!["config.yaml".to_string()]);
+    assert!(!watcher.poll());
+
+    // An editor saving three times in a row.
+    for _ in 0..3 {
+      backend.touch("config.yaml");
+      assert!(!watcher.poll());
+      backend.advance(200);
+    }
+    assert!(!watcher.poll());
+    backend.advance(300);
+    assert!(watcher.poll());
+
+    backend.advance(1000);
+    assert!(!watcher.poll());
+  }
+
+  #[test]
+  fn created_and_deleted_files_count_as_changes() {
+    let backend = FakeWatchBackend::new();
+    let mut watcher = Watcher::new(&backend, vec!["colors.json".to_string()]);
+
+    backend.touch("colors.json");
+    assert!(!watcher.poll());
+    backend.advance(500);
+    assert!(watcher.poll());
+
+    backend.modified.borrow_mut().clear();
+    assert!(!watcher.poll());
+    backend.advance(500);
+    assert!(watcher.poll());
+  }
+
+  #[test]
+  fn other_files_are_ignored() {
+    let backend = FakeWatchBackend::new();
+    let mut watcher = Watcher::new(&backend, vec!["config.yaml".to_string()]);
+    backend.touch("log.txt");
+    backend.advance(1000);
+    assert!(!watcher.poll());
+
+    watcher.set_files(vec!["config.yaml".to_string(), "log.txt".to_string()]);
+    backend.advance(1000);
+    assert!(!watcher.poll());
+  }
+
+  #[test]
+  fn broken_config_keeps_the_old_one() {
+    let _lock = testing::lock();
+    let path = get_file_path("config.yaml");
+    let config = |color: &str| {
+      format!(
+        "version: 2\nwindow_rules:\n  - match: \"Global\"\n    active_border_color: \"{}\"\n",
+        color
+      )
+    };
+    let active_border = || {
+      Config::get().window_rules[0]
+        .style(true)
+        .border_color
+        .unwrap()
+    };
+    fs::write(&path, config("#ff0000")).unwrap();
+    assert!(Config::reload());
+
+    let backend = FakeWatchBackend::new();
+    let (sender, receiver) = channel();
+    let mut watcher = Watcher::new(&backend, Config::get().watched_files());
+
+    fs::write(&path, "window_rules:\n  - match: \"Global\n").unwrap();
+    backend.touch(&path);
+    assert!(reload_if_changed(&mut watcher, &sender));
+    backend.advance(500);
+    assert!(reload_if_changed(&mut watcher, &sender));
+    assert!(receiver.try_recv().is_err());
+    assert_eq!(active_border(), Color::Rgb(255, 0, 0));
+
+    // Fixing it is picked up like any other change.
+    fs::write(&path, config("#00ff00")).unwrap();
+    backend.touch(&path);
+    assert!(reload_if_changed(&mut watcher, &sender));
+    backend.advance(500);
+    assert!(reload_if_changed(&mut watcher, &sender));
+    assert_eq!(receiver.try_recv(), Ok(WindowEvent::Refresh));
+    assert_eq!(active_border(), Color::Rgb(0, 255, 0));
+
+    // Nothing to tell once the scheduler is gone.
+    drop(receiver);
+    backend.touch(&path);
+    assert!(reload_if_changed(&mut watcher, &sender));
+    backend.advance(500);
+    assert!(!reload_if_changed(&mut watcher, &sender));
+  }
+}